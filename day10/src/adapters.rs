@@ -0,0 +1,223 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    BadNumber { line: usize, found: String },
+    BadSteps(String),
+    Overflow,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::BadNumber { line, found } => {
+                write!(f, "line {}: {:?} is not a joltage", line, found)
+            }
+            Error::BadSteps(steps) => write!(f, "bad step set {:?}", steps),
+            Error::Overflow => write!(f, "too many arrangements to count"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// The outlet, every adapter in order, and the device, which is rated one
+// largest step above the highest adapter.
+#[derive(Debug)]
+pub struct AdapterChain {
+    joltages: Vec<u64>,
+    steps: BTreeSet<u64>,
+}
+
+impl AdapterChain {
+    pub const DEFAULT_STEPS: [u64; 3] = [1, 2, 3];
+
+    pub fn new(mut adapters: Vec<u64>, steps: &[u64]) -> Result<Self, Error> {
+        let steps: BTreeSet<u64> = steps.iter().copied().collect();
+        if steps.is_empty() || steps.contains(&0) {
+            return Err(Error::BadSteps(format!("{:?}", steps)));
+        }
+        let largest = *steps.iter().next_back().unwrap();
+
+        adapters.push(0);
+        adapters.sort_unstable();
+        let device = adapters[adapters.len() - 1] + largest;
+        adapters.push(device);
+        Ok(Self {
+            joltages: adapters,
+            steps,
+        })
+    }
+
+    pub fn with_steps(self, steps: &[u64]) -> Result<Self, Error> {
+        let len = self.joltages.len();
+        Self::new(self.joltages[1..len - 1].to_vec(), steps)
+    }
+
+    // How often each difference occurs when every adapter is used.
+    pub fn histogram(&self) -> BTreeMap<u64, usize> {
+        self.joltages
+            .windows(2)
+            .fold(BTreeMap::new(), |mut memo, window| {
+                *memo.entry(window[1] - window[0]).or_insert(0) += 1;
+                memo
+            })
+    }
+
+    // Positions an adapter at `i` can plug into.
+    fn next(&self, i: usize) -> impl Iterator<Item = usize> + '_ {
+        let from = self.joltages[i];
+        (i + 1..self.joltages.len())
+            .take_while(move |&j| {
+                self.joltages[j] - from <= *self.steps.iter().next_back().unwrap()
+            })
+            .filter(move |&j| self.steps.contains(&(self.joltages[j] - from)))
+    }
+
+    // Ways to get from each position to the device, counted backwards.
+    fn ways(&self) -> Result<Vec<u128>, Error> {
+        let mut ways = vec![0_u128; self.joltages.len()];
+        let last = ways.len() - 1;
+        ways[last] = 1;
+        for i in (0..last).rev() {
+            ways[i] = self
+                .next(i)
+                .try_fold(0_u128, |total, j| total.checked_add(ways[j]))
+                .ok_or(Error::Overflow)?;
+        }
+        Ok(ways)
+    }
+
+    pub fn arrangements(&self) -> Result<u128, Error> {
+        Ok(self.ways()?[0])
+    }
+
+    // Every arrangement from the outlet to the device, lazily, in order.
+    pub fn enumerate(&self) -> Result<Arrangements<'_>, Error> {
+        let reachable = self.ways()?.iter().map(|&ways| ways > 0).collect();
+        Ok(Arrangements {
+            chain: self,
+            reachable,
+            stack: vec![vec![0]],
+        })
+    }
+}
+
+impl FromStr for AdapterChain {
+    type Err = Error;
+
+    // One joltage per line, chained with the puzzle's 1-3 jolt steps.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let adapters = s
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i, line.trim()))
+            .filter(|(_, line)| !line.is_empty())
+            .map(|(i, line)| {
+                line.parse::<u64>().map_err(|_| Error::BadNumber {
+                    line: i + 1,
+                    found: line.to_string(),
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Self::new(adapters, &Self::DEFAULT_STEPS)
+    }
+}
+
+pub struct Arrangements<'a> {
+    chain: &'a AdapterChain,
+    reachable: Vec<bool>,
+    stack: Vec<Vec<usize>>,
+}
+
+impl<'a> Iterator for Arrangements<'a> {
+    type Item = Vec<u64>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let last = self.chain.joltages.len() - 1;
+        while let Some(path) = self.stack.pop() {
+            let end = path[path.len() - 1];
+            if end == last {
+                return Some(path.iter().map(|&i| self.chain.joltages[i]).collect());
+            }
+            // Pushed in reverse so the smallest next joltage comes out first.
+            let next: Vec<usize> = self
+                .chain
+                .next(end)
+                .filter(|&j| self.reachable[j])
+                .collect();
+            for &j in next.iter().rev() {
+                let mut path = path.clone();
+                path.push(j);
+                self.stack.push(path);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SMALL: &str = "16\n10\n15\n5\n1\n11\n7\n19\n6\n12\n4";
+    const LARGE: &str = "28\n33\n18\n42\n31\n14\n46\n20\n48\n47\n24\n23\n49\n45\n19\n38\n39\n11\n1\n32\n25\n35\n8\n17\n7\n9\n4\n2\n34\n10\n3";
+
+    #[test]
+    fn it_counts_differences() {
+        let chain: AdapterChain = SMALL.parse().unwrap();
+        let histogram = chain.histogram();
+        assert_eq!(histogram.get(&1), Some(&7));
+        assert_eq!(histogram.get(&3), Some(&5));
+
+        let chain: AdapterChain = LARGE.parse().unwrap();
+        let histogram = chain.histogram();
+        assert_eq!(histogram.get(&1), Some(&22));
+        assert_eq!(histogram.get(&3), Some(&10));
+    }
+
+    #[test]
+    fn it_counts_and_enumerates_arrangements() {
+        let chain: AdapterChain = SMALL.parse().unwrap();
+        assert_eq!(chain.arrangements(), Ok(8));
+        let all: Vec<Vec<u64>> = chain.enumerate().unwrap().collect();
+        assert_eq!(all.len(), 8);
+        assert_eq!(all[0], vec![0, 1, 4, 5, 6, 7, 10, 11, 12, 15, 16, 19, 22]);
+        assert_eq!(all[7], vec![0, 1, 4, 7, 10, 12, 15, 16, 19, 22]);
+
+        let chain: AdapterChain = LARGE.parse().unwrap();
+        assert_eq!(chain.arrangements(), Ok(19208));
+        assert_eq!(chain.enumerate().unwrap().count(), 19208);
+    }
+
+    #[test]
+    fn it_uses_the_configured_steps() {
+        let chain: AdapterChain = "1\n2\n4\n8".parse().unwrap();
+        assert_eq!(chain.arrangements(), Ok(0));
+        assert_eq!(chain.enumerate().unwrap().next(), None);
+
+        let chain = chain.with_steps(&[1, 2, 4]).unwrap();
+        assert_eq!(chain.joltages, vec![0, 1, 2, 4, 8, 12]);
+        assert_eq!(chain.arrangements(), Ok(3));
+        assert_eq!(
+            chain.enumerate().unwrap().collect::<Vec<_>>(),
+            vec![
+                vec![0, 1, 2, 4, 8, 12],
+                vec![0, 2, 4, 8, 12],
+                vec![0, 4, 8, 12],
+            ]
+        );
+        assert!(matches!(
+            AdapterChain::new(vec![], &[]),
+            Err(Error::BadSteps(_))
+        ));
+    }
+
+    #[test]
+    fn it_detects_overflow() {
+        // Every subset of 200 consecutive joltages is a valid chain.
+        let chain = AdapterChain::new((1..200).collect(), &(1..=200).collect::<Vec<_>>()).unwrap();
+        assert_eq!(chain.arrangements(), Err(Error::Overflow));
+    }
+}
@@ -1,37 +1,48 @@
-fn main() -> Result<(), Box<dyn std::error::Error>>{
-    let input = std::fs::read_to_string("input.txt")?;
-    let mut nums : Vec<i64>= input.lines().map(|line| line.parse::<i64>()).collect::<Result<_, _>>()?;
-    nums.push(0);
-    nums.sort();
-    let max = nums.last().unwrap().clone();
-    nums.push(max + 3);
-    dbg!(&nums);
-    let count = nums.windows(2).fold(std::collections::HashMap::new(), |mut memo, window| {
-        let mut nums = window.iter();
-        let first = nums.next().unwrap();
-        let second = nums.next().unwrap();
-        let diff = second - first;
-        dbg!(diff);
-        *memo.entry(diff).or_insert(0) += 1;
-        memo
-    });
+mod adapters;
+
+use adapters::AdapterChain;
 
-    let mut map = std::collections::HashMap::new();
-    for num in nums {
-        let mut total : i64 = {
-            [num - 1, num - 2, num - 3].iter().map(|num| {
-                map.get(num).or(Some(&0)).unwrap()
-            }).sum::<i64>()
-        };
-        if total == 0 {
-            total = 1;
+// --steps 1,2,4 chains with a different step set and --list N prints the
+// first N arrangements.
+fn run_args(
+    mut chain: AdapterChain,
+    mut args: impl Iterator<Item = String>,
+) -> Result<AdapterChain, Box<dyn std::error::Error>> {
+    let mut list = None;
+    while let Some(flag) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value for {}", flag))?;
+        match flag.as_str() {
+            "--steps" => {
+                let steps = value
+                    .split(',')
+                    .map(|step| step.trim().parse::<u64>())
+                    .collect::<Result<Vec<_>, _>>()?;
+                chain = chain.with_steps(&steps)?;
+            }
+            "--list" => list = Some(value.parse::<usize>()?),
+            _ => return Err(format!("unknown flag {}", flag).into()),
         }
-        *map.entry(num).or_insert(0) += total;
     }
 
-    dbg!(&map);
+    if let Some(list) = list {
+        for arrangement in chain.enumerate()?.take(list) {
+            let joltages: Vec<String> = arrangement.iter().map(u64::to_string).collect();
+            println!("{}", joltages.join(" -> "));
+        }
+    }
+    Ok(chain)
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let input = std::fs::read_to_string("input.txt")?;
+    let chain: AdapterChain = input.parse()?;
+    let chain = run_args(chain, std::env::args().skip(1))?;
 
-    println!("part1: {}", count.get(&1).unwrap() * count.get(&3).unwrap());
-    println!("part1: {}", map.get(&max).unwrap());
+    let histogram = chain.histogram();
+    let count = |diff| histogram.get(&diff).copied().unwrap_or(0);
+    println!("part1: {}", count(1) * count(3));
+    println!("part2: {}", chain.arrangements()?);
     Ok(())
 }
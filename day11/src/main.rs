@@ -1,4 +1,11 @@
+mod rules;
+mod simulation;
+
+use rules::SeatRules;
+use simulation::{Outcome, Simulation};
+use std::collections::hash_map::{DefaultHasher, Entry, HashMap};
 use std::convert::TryFrom;
+use std::hash::{Hash, Hasher};
 use std::str::FromStr;
 
 #[derive(PartialEq, Eq)]
@@ -13,7 +20,7 @@ impl std::fmt::Debug for Map {
         let string = self
             .inner
             .chunks(self.width)
-            .map(|line| line.iter().map(|tile| char::from(tile)).collect::<String>())
+            .map(|line| line.iter().map(char::from).collect::<String>())
             .collect::<Vec<String>>()
             .join("\n");
 
@@ -37,95 +44,102 @@ impl Map {
         (1, 1),
     ];
 
-    fn next2(&mut self) -> bool {
-        let mut changes = vec![];
-        for i in 0..self.inner.len() {
-            let pos = self.from_index(i);
-            match &self.inner[i] {
-                Tile::EmptySeat => {
-                    if self.los_count(pos.0, pos.1) == 0 {
-                        changes.push(i);
-                    }
-                }
-                Tile::OccupiedSeat => {
-                    if pos.0 == self.width as isize - 1 {}
-                    if self.los_count(pos.0, pos.1) >= 5 {
-                        changes.push(i);
-                    }
-                }
-                _ => continue,
-            }
-        }
+    // Applies `rules` to every seat at once, returning whether anything
+    // changed.
+    fn step(&mut self, rules: &SeatRules) -> bool {
+        let changes: Vec<(usize, Tile)> = (0..self.inner.len())
+            .filter(|&i| self.inner[i] != Tile::Floor)
+            .filter_map(|i| {
+                let (x, y) = self.position(i);
+                let next = rules.next(&self.inner[i], self.visible_count(x, y, rules));
+                Some((i, next)).filter(|_| next != self.inner[i])
+            })
+            .collect();
 
-        changes.iter().for_each(|&i| match self.inner.get_mut(i) {
-            Some(tile @ Tile::EmptySeat) => *tile = Tile::OccupiedSeat,
-            Some(tile @ Tile::OccupiedSeat) => *tile = Tile::EmptySeat,
-            _ => unreachable!(),
-        });
+        for &(i, tile) in &changes {
+            self.inner[i] = tile;
+        }
 
         !changes.is_empty()
     }
 
-    fn next(&mut self) -> bool {
-        let mut changes = vec![];
-        for i in 0..self.inner.len() {
-            let pos = self.from_index(i);
-            match &self.inner[i] {
-                Tile::EmptySeat => {
-                    if self.adjacent_count(pos.0, pos.1) == 0 {
-                        changes.push(i);
-                    }
+    fn hash_tiles(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.inner.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    // `Simulation::run` for rules it can't handle: steps until the seating
+    // settles, repeats an earlier generation, or `max_generations` have run,
+    // checking a repeated hash the same way before reporting a cycle.
+    fn run(&mut self, rules: &SeatRules, max_generations: Option<usize>) -> Outcome {
+        let limited = |generation: usize| max_generations.is_some_and(|max| generation >= max);
+        let mut generation = 0;
+        let mut seen: HashMap<u64, usize> = HashMap::new();
+        seen.insert(self.hash_tiles(), generation);
+        loop {
+            if limited(generation) {
+                return Outcome::GenerationLimit(generation);
+            }
+            if !self.step(rules) {
+                return Outcome::Converged {
+                    generations: generation,
+                };
+            }
+            generation += 1;
+            let first_repeat = match seen.entry(self.hash_tiles()) {
+                Entry::Vacant(entry) => {
+                    entry.insert(generation);
+                    continue;
                 }
-                Tile::OccupiedSeat => {
-                    if pos.0 == self.width as isize - 1 {}
-                    if self.adjacent_count(pos.0, pos.1) >= 4 {
-                        changes.push(i);
-                    }
+                Entry::Occupied(entry) => *entry.get(),
+            };
+
+            let period = generation - first_repeat;
+            let snapshot = self.inner.clone();
+            for _ in 0..period {
+                if limited(generation) {
+                    return Outcome::GenerationLimit(generation);
                 }
-                _ => continue,
+                if !self.step(rules) {
+                    return Outcome::Converged {
+                        generations: generation,
+                    };
+                }
+                generation += 1;
+            }
+            if self.inner == snapshot {
+                return Outcome::Cycle {
+                    first_repeat,
+                    period,
+                };
             }
+            seen.insert(self.hash_tiles(), generation);
         }
-
-        changes.iter().for_each(|&i| match self.inner.get_mut(i) {
-            Some(tile @ Tile::EmptySeat) => *tile = Tile::OccupiedSeat,
-            Some(tile @ Tile::OccupiedSeat) => *tile = Tile::EmptySeat,
-            _ => unreachable!(),
-        });
-
-        !changes.is_empty()
     }
 
-    fn adjacent_count(&self, x: isize, y: isize) -> u32 {
+    fn visible_count(&self, x: isize, y: isize, rules: &SeatRules) -> u32 {
+        let range = rules.neighbourhood.range();
         Self::POSITIONS
             .iter()
             .map(|(o_x, o_y)| {
-                let pos = (x + o_x, y + o_y);
-                if let Some(Tile::OccupiedSeat) = self.read(x + o_x, y + o_y) {
-                    1
-                } else {
-                    0
-                }
-            })
-            .sum()
-    }
-
-    fn los_count(&self, x: isize, y: isize) -> u32 {
-        Self::POSITIONS
-            .iter()
-            .map(|(o_x, o_y)| {
-                let mut offset_x = *o_x;
-                let mut offset_y = *o_y;
-                loop {
-                    match self.read(x + offset_x, y + offset_y) {
-                        Some(Tile::EmptySeat) => return 0,
-                        Some(Tile::OccupiedSeat) => return 1,
-                        Some(Tile::Floor) => {
-                            offset_x += o_x;
-                            offset_y += o_y;
-                        }
-                        None => return 0,
+                let mut count = 0;
+                let mut distance = 1;
+                while range.is_none_or(|range| distance <= range) {
+                    let d = distance as isize;
+                    let tile = match self.read(x + o_x * d, y + o_y * d) {
+                        Some(tile) => tile,
+                        None => break,
+                    };
+                    if tile == &Tile::OccupiedSeat {
+                        count += 1;
+                    }
+                    if rules.blocks(tile) {
+                        break;
                     }
+                    distance += 1;
                 }
+                count
             })
             .sum()
     }
@@ -140,7 +154,7 @@ impl Map {
         self.inner.get(self.width * y as usize + x as usize)
     }
 
-    fn from_index(&self, index: usize) -> (isize, isize) {
+    fn position(&self, index: usize) -> (isize, isize) {
         let y = index / self.width;
         let x = index % self.width;
         (x as isize, y as isize)
@@ -160,8 +174,8 @@ impl Map {
     }
 }
 
-#[derive(Debug, PartialEq, Eq)]
-enum Tile {
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tile {
     EmptySeat,
     OccupiedSeat,
     Floor,
@@ -169,7 +183,7 @@ enum Tile {
 
 impl std::fmt::Display for Tile {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self)
+        write!(f, "{}", char::from(self))
     }
 }
 
@@ -201,7 +215,7 @@ impl std::convert::TryFrom<&char> for Tile {
 }
 
 #[derive(Debug)]
-enum ParseMapError {
+pub enum ParseMapError {
     UnknownChar(String),
 }
 impl FromStr for Map {
@@ -225,32 +239,100 @@ impl FromStr for Map {
 
 impl std::fmt::Display for ParseMapError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self)
+        match self {
+            ParseMapError::UnknownChar(c) => write!(f, "unknown tile {:?}", c),
+        }
     }
 }
 
 impl std::error::Error for ParseMapError {}
+// --neighbourhood adjacent|sight|sight:N, --occupy N, --vacate N and
+// --blocking TILES (like "L#") simulate a custom policy, starting from the
+// puzzle's part 1 rules. --show also prints the final seating and
+// --max-generations N changes how long to wait for it to settle.
+fn run_args(
+    input: &str,
+    mut args: impl Iterator<Item = String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut rules = None;
+    let mut show = false;
+    let mut max_generations = MAX_GENERATIONS;
+    while let Some(flag) = args.next() {
+        if flag == "--show" {
+            show = true;
+            continue;
+        }
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value for {}", flag))?;
+        if flag == "--max-generations" {
+            max_generations = value.parse()?;
+            continue;
+        }
+        let rules = rules.get_or_insert_with(SeatRules::adjacent);
+        match flag.as_str() {
+            "--neighbourhood" => rules.neighbourhood = value.parse()?,
+            "--occupy" => rules.occupy_at_most = value.parse()?,
+            "--vacate" => rules.vacate_at_least = value.parse()?,
+            "--blocking" => {
+                rules.blocking = value
+                    .chars()
+                    .map(|c| Tile::try_from(&c))
+                    .collect::<Result<_, _>>()?
+            }
+            _ => return Err(format!("unknown flag {}", flag).into()),
+        }
+    }
+
+    // Rules where only one kind of seat blocks sight can't be precomputed,
+    // so those run on the map directly.
+    if let Some(rules) = rules {
+        let mut map: Map = input.parse()?;
+        let outcome = match Simulation::new(&map, rules.clone()) {
+            Ok(mut simulation) => {
+                let outcome = simulation.run(Some(max_generations));
+                simulation.write_to(&mut map);
+                outcome
+            }
+            Err(_) => map.run(&rules, Some(max_generations)),
+        };
+        println!("{:?}", rules);
+        println!("{:?}: {}", outcome, map.count_occupied_seats());
+        if show {
+            println!("{:?}", map);
+        }
+    }
+    Ok(())
+}
+
+const MAX_GENERATIONS: usize = 10_000;
+
+// Occupied seats once `rules` stop changing anything.
+fn settle(map: &Map, rules: SeatRules) -> Result<usize, Box<dyn std::error::Error>> {
+    let mut simulation = Simulation::new(map, rules)?;
+    match simulation.run(Some(MAX_GENERATIONS)) {
+        Outcome::Converged { .. } => Ok(simulation.occupied()),
+        outcome => Err(format!("seating never settled: {:?}", outcome).into()),
+    }
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let input = std::fs::read_to_string("input.txt")?;
-    let mut map: Map = input.parse()?;
-    while map.next() {}
+    let map: Map = input.parse()?;
 
-    let part1 = map.count_occupied_seats();
+    let part1 = settle(&map, SeatRules::adjacent())?;
     println!("part1: {}", part1);
 
-    let mut map: Map = input.parse()?;
-    while map.next2() {
-        println!("{}", map.count_occupied_seats());
-    }
-
-    let part2 = map.count_occupied_seats();
+    let part2 = settle(&map, SeatRules::line_of_sight())?;
     println!("part2: {}", part2);
-    Ok(())
+
+    run_args(&input, std::env::args().skip(1))
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::rules::Neighbourhood;
 
     #[test]
     fn it_works() {
@@ -278,7 +360,7 @@ mod test {
             .parse()
             .unwrap();
 
-        map.next();
+        map.step(&SeatRules::adjacent());
         assert_eq!(map, round1);
 
         let round2: Map = r#"#.LL.L#.##
@@ -294,7 +376,7 @@ L.L.L..L..
             .parse()
             .unwrap();
 
-        map.next();
+        map.step(&SeatRules::adjacent());
         assert_eq!(map, round2);
     }
 
@@ -312,7 +394,7 @@ L.L.L..L..
             #.#####.##"#
             .parse()
             .unwrap();
-        assert_eq!(round1.adjacent_count(9, 0), 3);
+        assert_eq!(round1.visible_count(9, 0, &SeatRules::adjacent()), 3);
     }
 
     #[test]
@@ -328,7 +410,7 @@ L.L.L..L..
             L.LLLLLL.L
             L.LLLLL.LL"#;
         let mut map: Map = input.parse().unwrap();
-        map.next2();
+        map.step(&SeatRules::line_of_sight());
         let round1: Map = r#"#.##.##.##
             #######.##
             #.#.#..#..
@@ -343,7 +425,7 @@ L.L.L..L..
             .unwrap();
         assert_eq!(map, round1);
 
-        map.next2();
+        map.step(&SeatRules::line_of_sight());
         let round2: Map = r#"#.LL.LL.L#
             #LLLLLL.LL
             L.L.L..L..
@@ -373,6 +455,97 @@ L.L.L..L..
             #.#####.##"#
             .parse()
             .unwrap();
-        assert_eq!(map.los_count(6, 0), 5);
+        assert_eq!(map.visible_count(6, 0, &SeatRules::line_of_sight()), 5);
+    }
+
+    #[test]
+    fn it_follows_custom_rules() {
+        let input = r#"L.LL.LL.LL
+            LLLLLLL.LL
+            L.L.L..L..
+            LLLL.LL.LL
+            L.LL.LL.LL
+            L.LLLLL.LL
+            ..L.L.....
+            LLLLLLLLLL
+            L.LLLLLL.L
+            L.LLLLL.LL"#;
+        let settle = |rules: &SeatRules| {
+            let mut map: Map = input.parse().unwrap();
+            while map.step(rules) {}
+            map.count_occupied_seats()
+        };
+
+        assert_eq!(settle(&SeatRules::adjacent()), 37);
+        assert_eq!(settle(&SeatRules::line_of_sight()), 26);
+
+        // Looking one tile away, or with floor blocking sight, is just
+        // adjacency.
+        let within_one = SeatRules {
+            neighbourhood: Neighbourhood::LineOfSightWithin(1),
+            vacate_at_least: 4,
+            ..SeatRules::line_of_sight()
+        };
+        assert_eq!(settle(&within_one), 37);
+        let floor_blocks = SeatRules {
+            blocking: vec![Tile::EmptySeat, Tile::OccupiedSeat, Tile::Floor],
+            vacate_at_least: 4,
+            ..SeatRules::line_of_sight()
+        };
+        assert_eq!(settle(&floor_blocks), 37);
+
+        // Nobody ever leaves, so every seat fills in the first round.
+        let never_vacate = SeatRules {
+            vacate_at_least: 9,
+            ..SeatRules::adjacent()
+        };
+        assert_eq!(settle(&never_vacate), 71);
+    }
+
+    #[test]
+    fn it_detects_cycles_without_a_simulation() {
+        let input = "L.LL.LL.LL\nLLLLLLL.LL\nL.L.L..L..\nLLLL.LL.LL";
+        let map: Map = input.parse().unwrap();
+
+        // Every seat flips every generation, whatever blocks sight.
+        let flip = SeatRules {
+            occupy_at_most: 8,
+            vacate_at_least: 0,
+            ..SeatRules::line_of_sight()
+        };
+        let only_empty_blocks = SeatRules {
+            blocking: vec![Tile::EmptySeat],
+            ..flip.clone()
+        };
+        assert!(Simulation::new(&map, only_empty_blocks.clone()).is_err());
+        let cycle = Outcome::Cycle {
+            first_repeat: 0,
+            period: 2,
+        };
+        let mut simulation = Simulation::new(&map, flip).unwrap();
+        assert_eq!(simulation.run(None), cycle);
+        let mut flipping: Map = input.parse().unwrap();
+        assert_eq!(flipping.run(&only_empty_blocks, None), cycle);
+        let mut flipping: Map = input.parse().unwrap();
+        assert_eq!(
+            flipping.run(&only_empty_blocks, Some(1)),
+            Outcome::GenerationLimit(1)
+        );
+
+        let only_occupied_blocks = SeatRules {
+            blocking: vec![Tile::OccupiedSeat],
+            ..SeatRules::line_of_sight()
+        };
+        let mut stepped: Map = input.parse().unwrap();
+        let mut generations = 0;
+        while stepped.step(&only_occupied_blocks) {
+            generations += 1;
+        }
+        let mut settled: Map = input.parse().unwrap();
+        assert_eq!(
+            settled.run(&only_occupied_blocks, None),
+            Outcome::Converged { generations }
+        );
+        assert_eq!(settled, stepped);
     }
 }
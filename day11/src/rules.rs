@@ -0,0 +1,95 @@
+use crate::Tile;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Neighbourhood {
+    Adjacent,
+    LineOfSight,
+    LineOfSightWithin(usize),
+}
+
+impl Neighbourhood {
+    // How far along each of the eight directions to look.
+    pub fn range(&self) -> Option<usize> {
+        match *self {
+            Neighbourhood::Adjacent => Some(1),
+            Neighbourhood::LineOfSight => None,
+            Neighbourhood::LineOfSightWithin(distance) => Some(distance),
+        }
+    }
+}
+
+// `adjacent`, `sight` or `sight:N`.
+impl FromStr for Neighbourhood {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "adjacent" => Ok(Neighbourhood::Adjacent),
+            "sight" => Ok(Neighbourhood::LineOfSight),
+            _ => s
+                .strip_prefix("sight:")
+                .and_then(|distance| distance.parse().ok())
+                .map(Neighbourhood::LineOfSightWithin)
+                .ok_or_else(|| format!("unknown neighbourhood {:?}", s)),
+        }
+    }
+}
+
+// How a seat reacts to the occupied seats it can see. Looking in each
+// direction, every occupied seat in range counts, and the first tile that
+// blocks sight ends the look.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeatRules {
+    pub neighbourhood: Neighbourhood,
+    // An empty seat fills up when it sees at most this many occupied seats.
+    pub occupy_at_most: u32,
+    // An occupied seat empties when it sees at least this many.
+    pub vacate_at_least: u32,
+    pub blocking: Vec<Tile>,
+}
+
+impl SeatRules {
+    pub fn adjacent() -> Self {
+        Self {
+            neighbourhood: Neighbourhood::Adjacent,
+            occupy_at_most: 0,
+            vacate_at_least: 4,
+            blocking: vec![Tile::EmptySeat, Tile::OccupiedSeat],
+        }
+    }
+
+    pub fn line_of_sight() -> Self {
+        Self {
+            neighbourhood: Neighbourhood::LineOfSight,
+            vacate_at_least: 5,
+            ..Self::adjacent()
+        }
+    }
+
+    pub fn blocks(&self, tile: &Tile) -> bool {
+        self.blocking.contains(tile)
+    }
+
+    // What `tile` turns into when it can see `occupied` occupied seats.
+    pub fn next(&self, tile: &Tile, occupied: u32) -> Tile {
+        match tile {
+            Tile::EmptySeat if occupied <= self.occupy_at_most => Tile::OccupiedSeat,
+            Tile::OccupiedSeat if occupied >= self.vacate_at_least => Tile::EmptySeat,
+            tile => *tile,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_parses_neighbourhoods() {
+        assert_eq!("adjacent".parse(), Ok(Neighbourhood::Adjacent));
+        assert_eq!("sight".parse(), Ok(Neighbourhood::LineOfSight));
+        assert_eq!("sight:3".parse(), Ok(Neighbourhood::LineOfSightWithin(3)));
+        assert!("sight:".parse::<Neighbourhood>().is_err());
+    }
+}
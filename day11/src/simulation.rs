@@ -0,0 +1,395 @@
+use crate::rules::SeatRules;
+use crate::{Map, Tile};
+use std::collections::hash_map::{Entry, HashMap};
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    // Which seats are visible would change as seats fill and empty.
+    StateDependentSight,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::StateDependentSight => write!(
+                f,
+                "empty and occupied seats must either both block sight or both not"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// How a `Simulation::run` ended. Generations count from 0, the starting map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    // Nothing changed after the last of `generations` steps.
+    Converged { generations: usize },
+    // Generation `first_repeat + period` is the same as `first_repeat`, and
+    // the seating keeps going round from there.
+    Cycle { first_repeat: usize, period: usize },
+    GenerationLimit(usize),
+}
+
+// A `Map::step` loop that skips work the map redoes every generation. Floor
+// never changes, and as long as empty and occupied seats block sight the same
+// way, neither does who can see whom, so each seat's neighbours are found once
+// up front.
+//
+// After that, a seat whose neighbourhood looks the same as it did two
+// generations ago will do what it did then. That covers seats that have
+// settled, and also the big regions that flip between full and empty every
+// generation until the settled edge reaches them, which is where a large map
+// spends most of its time. Those seats keep the value already sitting in the
+// spare buffer, so a generation only looks at seats near something that
+// didn't repeat.
+pub struct Simulation {
+    rules: SeatRules,
+    // The map index of each seat; seats are numbered in map order.
+    seats: Vec<usize>,
+    // Seat `s` sees `neighbours[offsets[s]..offsets[s + 1]]`.
+    offsets: Vec<usize>,
+    neighbours: Vec<u32>,
+    current: Vec<bool>,
+    // The generation before `current`, which becomes the next one once the
+    // seats that don't repeat it are updated.
+    next: Vec<bool>,
+    // Seats to look at next step.
+    dirty: Vec<u32>,
+    queued: Vec<bool>,
+    // How many seats differ between `next` and `current`.
+    differing: usize,
+    generation: usize,
+    // The XOR of `zobrist` over the occupied seats of `current` and `next`.
+    hash: u64,
+    next_hash: u64,
+}
+
+// A fixed pseudo-random key for each seat (splitmix64), so a generation's
+// hash can be kept up to date from just the seats that changed.
+fn zobrist(seat: usize) -> u64 {
+    let mut z = (seat as u64)
+        .wrapping_add(1)
+        .wrapping_mul(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+impl Simulation {
+    pub fn new(map: &Map, rules: SeatRules) -> Result<Self, Error> {
+        if rules.blocks(&Tile::EmptySeat) != rules.blocks(&Tile::OccupiedSeat) {
+            return Err(Error::StateDependentSight);
+        }
+
+        let mut ids = vec![u32::MAX; map.inner.len()];
+        let mut seats = vec![];
+        for (i, tile) in map.inner.iter().enumerate() {
+            if tile != &Tile::Floor {
+                ids[i] = seats.len() as u32;
+                seats.push(i);
+            }
+        }
+
+        let range = rules.neighbourhood.range();
+        let mut offsets = vec![0];
+        let mut neighbours = vec![];
+        for &i in &seats {
+            let (x, y) = map.position(i);
+            for (o_x, o_y) in Map::POSITIONS.iter() {
+                let mut distance = 1;
+                while range.is_none_or(|range| distance <= range) {
+                    let d = distance as isize;
+                    let (x, y) = (x + o_x * d, y + o_y * d);
+                    let tile = match map.read(x, y) {
+                        Some(tile) => tile,
+                        None => break,
+                    };
+                    if tile != &Tile::Floor {
+                        neighbours.push(ids[map.width * y as usize + x as usize]);
+                    }
+                    if rules.blocks(tile) {
+                        break;
+                    }
+                    distance += 1;
+                }
+            }
+            offsets.push(neighbours.len());
+        }
+
+        let current: Vec<bool> = seats
+            .iter()
+            .map(|&i| map.inner[i] == Tile::OccupiedSeat)
+            .collect();
+        let hash = (0..seats.len())
+            .filter(|&seat| current[seat])
+            .fold(0, |hash, seat| hash ^ zobrist(seat));
+        Ok(Self {
+            rules,
+            hash,
+            next_hash: hash,
+            differing: 0,
+            next: current.clone(),
+            current,
+            dirty: (0..seats.len() as u32).collect(),
+            queued: vec![true; seats.len()],
+            generation: 0,
+            seats,
+            offsets,
+            neighbours,
+        })
+    }
+
+    fn neighbours(&self, seat: usize) -> &[u32] {
+        &self.neighbours[self.offsets[seat]..self.offsets[seat + 1]]
+    }
+
+    // Runs one generation, returning whether anything changed.
+    pub fn step(&mut self) -> bool {
+        // Seats whose new value isn't the one from two generations ago.
+        let mut changed = vec![];
+        for &seat in &self.dirty {
+            let seat = seat as usize;
+            self.queued[seat] = false;
+            let occupied = self
+                .neighbours(seat)
+                .iter()
+                .filter(|&&n| self.current[n as usize])
+                .count() as u32;
+            let tile = if self.current[seat] {
+                Tile::OccupiedSeat
+            } else {
+                Tile::EmptySeat
+            };
+            let next = self.rules.next(&tile, occupied) == Tile::OccupiedSeat;
+            if next != self.next[seat] {
+                // Exactly one of the old and new values matches `current`.
+                if next == self.current[seat] {
+                    self.differing -= 1;
+                } else {
+                    self.differing += 1;
+                }
+                self.next[seat] = next;
+                self.next_hash ^= zobrist(seat);
+                changed.push(seat);
+            }
+        }
+
+        std::mem::swap(&mut self.current, &mut self.next);
+        std::mem::swap(&mut self.hash, &mut self.next_hash);
+        self.dirty.clear();
+        for &seat in &changed {
+            for i in self.offsets[seat]..self.offsets[seat + 1] {
+                let n = self.neighbours[i];
+                if !self.queued[n as usize] {
+                    self.queued[n as usize] = true;
+                    self.dirty.push(n);
+                }
+            }
+            if !self.queued[seat] {
+                self.queued[seat] = true;
+                self.dirty.push(seat as u32);
+            }
+        }
+
+        if self.differing > 0 {
+            self.generation += 1;
+        }
+        self.differing > 0
+    }
+
+    // Steps until the seating settles, repeats an earlier generation, or
+    // `max_generations` have run. Only a hash of each generation is kept; when
+    // one comes round again, going round once more and comparing the whole
+    // seating rules out a hash collision before a cycle is reported.
+    pub fn run(&mut self, max_generations: Option<usize>) -> Outcome {
+        let limited = |generation: usize| max_generations.is_some_and(|max| generation >= max);
+        let mut seen: HashMap<u64, usize> = HashMap::new();
+        seen.insert(self.hash, self.generation);
+        loop {
+            if limited(self.generation) {
+                return Outcome::GenerationLimit(self.generation);
+            }
+            if !self.step() {
+                return Outcome::Converged {
+                    generations: self.generation,
+                };
+            }
+            let first_repeat = match seen.entry(self.hash) {
+                Entry::Vacant(entry) => {
+                    entry.insert(self.generation);
+                    continue;
+                }
+                Entry::Occupied(entry) => *entry.get(),
+            };
+
+            let period = self.generation - first_repeat;
+            let snapshot = self.current.clone();
+            for _ in 0..period {
+                if limited(self.generation) {
+                    return Outcome::GenerationLimit(self.generation);
+                }
+                if !self.step() {
+                    return Outcome::Converged {
+                        generations: self.generation,
+                    };
+                }
+            }
+            if self.current == snapshot {
+                return Outcome::Cycle {
+                    first_repeat,
+                    period,
+                };
+            }
+            seen.insert(self.hash, self.generation);
+        }
+    }
+
+    pub fn occupied(&self) -> usize {
+        self.current.iter().filter(|&&occupied| occupied).count()
+    }
+
+    pub fn write_to(&self, map: &mut Map) {
+        for (seat, &i) in self.seats.iter().enumerate() {
+            map.inner[i] = if self.current[seat] {
+                Tile::OccupiedSeat
+            } else {
+                Tile::EmptySeat
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rules::Neighbourhood;
+
+    // A deterministic jumble of floor and empty seats.
+    fn random_map(width: usize, height: usize, seed: u64) -> Map {
+        let mut state = seed;
+        let inner = (0..width * height)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                if state >> 61 < 2 {
+                    Tile::Floor
+                } else {
+                    Tile::EmptySeat
+                }
+            })
+            .collect();
+        Map {
+            inner,
+            width,
+            height,
+        }
+    }
+
+    fn rule_sets() -> Vec<SeatRules> {
+        vec![
+            SeatRules::adjacent(),
+            SeatRules::line_of_sight(),
+            SeatRules {
+                neighbourhood: Neighbourhood::LineOfSightWithin(3),
+                ..SeatRules::line_of_sight()
+            },
+            SeatRules {
+                blocking: vec![],
+                vacate_at_least: 6,
+                occupy_at_most: 1,
+                ..SeatRules::line_of_sight()
+            },
+            SeatRules {
+                blocking: vec![Tile::Floor, Tile::EmptySeat, Tile::OccupiedSeat],
+                ..SeatRules::line_of_sight()
+            },
+        ]
+    }
+
+    #[test]
+    fn it_matches_the_map_generation_by_generation() {
+        for seed in 0..4 {
+            for rules in rule_sets() {
+                let mut map = random_map(23, 17, seed);
+                let mut simulation = Simulation::new(&map, rules.clone()).unwrap();
+                let mut fast = random_map(23, 17, seed);
+
+                for _ in 0..200 {
+                    let changed = map.step(&rules);
+                    assert_eq!(simulation.step(), changed, "{:?}", rules);
+                    simulation.write_to(&mut fast);
+                    assert_eq!(fast, map, "{:?}", rules);
+                    let hash = (0..simulation.seats.len())
+                        .filter(|&seat| simulation.current[seat])
+                        .fold(0, |hash, seat| hash ^ zobrist(seat));
+                    assert_eq!(simulation.hash, hash);
+                    if !changed {
+                        break;
+                    }
+                }
+                assert_eq!(simulation.occupied(), map.count_occupied_seats());
+            }
+        }
+    }
+
+    #[test]
+    fn it_converges_on_large_maps() {
+        // Four million tiles, most of which flip every generation for well
+        // over a thousand generations before settling.
+        let mut map = random_map(2000, 2000, 7);
+        let rules = SeatRules::line_of_sight();
+        let mut simulation = Simulation::new(&map, rules.clone()).unwrap();
+        match simulation.run(None) {
+            Outcome::Converged { generations } => assert!(generations > 1000),
+            outcome => panic!("expected convergence, got {:?}", outcome),
+        }
+        simulation.write_to(&mut map);
+        assert!(!map.step(&rules));
+    }
+
+    #[test]
+    fn it_rejects_state_dependent_sight() {
+        let map = random_map(3, 3, 0);
+        let rules = SeatRules {
+            blocking: vec![Tile::OccupiedSeat],
+            ..SeatRules::line_of_sight()
+        };
+        assert!(matches!(
+            Simulation::new(&map, rules),
+            Err(Error::StateDependentSight)
+        ));
+    }
+
+    #[test]
+    fn it_detects_cycles_and_limits() {
+        let map = random_map(10, 10, 3);
+
+        // Every seat flips every generation.
+        let flip = SeatRules {
+            occupy_at_most: 8,
+            vacate_at_least: 0,
+            ..SeatRules::adjacent()
+        };
+        let mut simulation = Simulation::new(&map, flip.clone()).unwrap();
+        assert_eq!(
+            simulation.run(None),
+            Outcome::Cycle {
+                first_repeat: 0,
+                period: 2
+            }
+        );
+        let mut simulation = Simulation::new(&map, flip).unwrap();
+        assert_eq!(simulation.run(Some(1)), Outcome::GenerationLimit(1));
+
+        let mut simulation = Simulation::new(&map, SeatRules::adjacent()).unwrap();
+        let mut map = map;
+        let mut generations = 0;
+        while map.step(&SeatRules::adjacent()) {
+            generations += 1;
+        }
+        assert_eq!(simulation.run(None), Outcome::Converged { generations });
+    }
+}
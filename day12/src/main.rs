@@ -1,6 +1,12 @@
+mod navigation;
+mod track;
+
+use navigation::{NavigationMode, Ship};
 use std::str::FromStr;
+use track::Track;
 
-enum Instruction {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction {
     N(i32),
     E(i32),
     S(i32),
@@ -10,14 +16,24 @@ enum Instruction {
     F(i32),
 }
 
-#[derive(Debug)]
-enum Error {
+#[derive(Debug, PartialEq)]
+pub enum Error {
     InstructionParseError,
+    NonRightAngle(i32),
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self)
+        match self {
+            Error::InstructionParseError => write!(f, "couldn't parse instruction"),
+            Error::NonRightAngle(degrees) => {
+                write!(
+                    f,
+                    "can only turn in multiples of 90 degrees, not {}",
+                    degrees
+                )
+            }
+        }
     }
 }
 
@@ -50,170 +66,221 @@ impl FromStr for Instruction {
     }
 }
 
-#[derive(PartialEq, Eq, Copy, Clone)]
-enum Direction {
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use Instruction::*;
+
+        match self {
+            N(val) => write!(f, "N{}", val),
+            E(val) => write!(f, "E{}", val),
+            S(val) => write!(f, "S{}", val),
+            W(val) => write!(f, "W{}", val),
+            L(val) => write!(f, "L{}", val),
+            R(val) => write!(f, "R{}", val),
+            F(val) => write!(f, "F{}", val),
+        }
+    }
+}
+
+// Quarter turns in `degrees`, which must be a whole number of them.
+pub fn quarter_turns(degrees: i32) -> Result<i32, Error> {
+    if degrees % 90 != 0 {
+        return Err(Error::NonRightAngle(degrees));
+    }
+    Ok(degrees / 90)
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum Direction {
     N,
     S,
     E,
     W,
 }
 
-enum Hand {
+pub enum Hand {
     Left,
     Right,
 }
 
+impl std::fmt::Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let c = match self {
+            Direction::N => 'N',
+            Direction::S => 'S',
+            Direction::E => 'E',
+            Direction::W => 'W',
+        };
+        write!(f, "{}", c)
+    }
+}
+
 impl Direction {
     const DIRECTIONS_IN_ORDER: [Direction; 4] =
         [Direction::N, Direction::E, Direction::S, Direction::W];
     const DIR_LEN: usize = Self::DIRECTIONS_IN_ORDER.len();
-    fn turn(&self, degrees: i32, rotating: Hand) -> Direction {
-        let ticks = degrees / 90;
+
+    fn offset(&self) -> (i32, i32) {
+        match self {
+            Direction::N => (0, 1),
+            Direction::S => (0, -1),
+            Direction::E => (1, 0),
+            Direction::W => (-1, 0),
+        }
+    }
+
+    fn turn(&self, degrees: i32, rotating: Hand) -> Result<Direction, Error> {
+        let ticks = quarter_turns(degrees)?;
         let current_pos = Self::DIRECTIONS_IN_ORDER
             .iter()
-            .position(|dir| &dir == &self)
+            .position(|dir| dir == self)
             .unwrap();
         let new_pos = match rotating {
             Hand::Left => current_pos as i32 - ticks,
             Hand::Right => current_pos as i32 + ticks,
         }
         .rem_euclid(Self::DIR_LEN as i32);
-        Self::DIRECTIONS_IN_ORDER[new_pos as usize]
+        Ok(Self::DIRECTIONS_IN_ORDER[new_pos as usize])
     }
 }
 
-struct Boat {
-    x: i32,
-    y: i32,
-    direction: Direction,
-}
+fn plot_course(
+    mode: &dyn NavigationMode,
+    instructions: impl Iterator<Item = Result<Instruction, Error>>,
+) -> Result<Track, Error> {
+    let mut ship = Ship::default();
+    let mut track = Track::default();
+    let record = |track: &mut Track, instruction: Option<&Instruction>, ship: &Ship| {
+        if mode.uses_waypoint() {
+            track.record(instruction, (ship.x, ship.y), None, Some(ship.waypoint));
+        } else {
+            track.record(instruction, (ship.x, ship.y), Some(ship.heading), None);
+        }
+    };
 
-impl Boat {
-    fn apply(&mut self, instruction: Instruction) -> Result<(), Error> {
-        use Instruction::*;
+    record(&mut track, None, &ship);
+    for instruction in instructions {
+        let instruction = instruction?;
+        mode.apply(&mut ship, instruction)?;
+        record(&mut track, Some(&instruction), &ship);
+    }
 
-        fn match_instruction(boat: &mut Boat, instruction: Instruction) {
-            match instruction {
-                N(val) => boat.y += val,
-                E(val) => boat.x += val,
-                S(val) => boat.y -= val,
-                W(val) => boat.x -= val,
-                L(degrees) => boat.direction = boat.direction.turn(degrees, Hand::Left),
-                R(degrees) => boat.direction = boat.direction.turn(degrees, Hand::Right),
-                _ => unreachable!(),
-            }
-        }
+    Ok(track)
+}
 
-        match instruction {
-            F(val) => match_instruction(
-                self,
-                match self.direction {
-                    Direction::N => Instruction::N(val),
-                    Direction::S => Instruction::S(val),
-                    Direction::E => Instruction::E(val),
-                    Direction::W => Instruction::W(val),
-                },
-            ),
-            ins => match_instruction(self, ins),
+// --csv MODE or --geojson MODE prints the track for MODE, which is
+// `heading` (part 1), `waypoint` (part 2) or `carried`. --distance MODE just
+// prints how far the ship ended up.
+fn run_args(
+    input: &str,
+    mut args: impl Iterator<Item = String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    while let Some(flag) = args.next() {
+        let mode = args
+            .next()
+            .ok_or_else(|| format!("missing mode for {}", flag))?;
+        let instructions = input.lines().map(|line| line.parse::<Instruction>());
+        let mode = navigation::mode(&mode).ok_or_else(|| format!("unknown mode {}", mode))?;
+        let track = plot_course(mode.as_ref(), instructions)?;
+        match flag.as_str() {
+            "--distance" => println!("{}", track.distance()),
+            "--csv" => print!("{}", track.to_csv()),
+            "--geojson" => println!("{}", track.to_geojson()),
+            _ => return Err(format!("unknown flag {}", flag).into()),
         }
-
-        Ok(())
     }
+    Ok(())
 }
 
-struct BoatWithWaypoint {
-    x: i32,
-    y: i32,
-    direction: Direction,
-    waypoint: Waypoint,
-}
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let input = std::fs::read_to_string("input.txt")?;
+    let instructions = input.lines().map(|line| line.parse::<Instruction>());
+    let part1 = plot_course(&navigation::Heading, instructions.clone())?.distance();
+    let part2 = plot_course(&navigation::Waypoint, instructions)?.distance();
+    println!("part1: {}", part1);
+    println!("part2: {}", part2);
 
-struct Waypoint {
-    rel_x: i32,
-    rel_y: i32,
+    run_args(&input, std::env::args().skip(1))
 }
 
-impl BoatWithWaypoint {
-    fn apply(&mut self, instruction: Instruction) -> Result<(), Error> {
-        use Instruction::*;
-        fn translate(mut degrees: i32, rotation: Hand, boat: &mut BoatWithWaypoint) {
-            let x = boat.waypoint.rel_x;
-            let y = boat.waypoint.rel_y;
-
-            if let Hand::Left = rotation {
-                degrees *= -1;
-            }
-            let quadrant = (degrees / 90).rem_euclid(4);
-
-            let (rel_x, rel_y) = match quadrant {
-                0 => (x, y),   // 1, 2
-                1 => (y, -x),  // 2, -1
-                2 => (-x, -y), // -1, -2
-                3 => (-y, x),  // -2, 1
-                _ => unreachable!(),
-            };
-            boat.waypoint.rel_x = rel_x;
-            boat.waypoint.rel_y = rel_y;
-        }
+#[cfg(test)]
+mod test {
+    use super::*;
 
-        match instruction {
-            N(val) => self.waypoint.rel_y += val,
-            E(val) => self.waypoint.rel_x += val,
-            S(val) => self.waypoint.rel_y -= val,
-            W(val) => self.waypoint.rel_x -= val,
-            L(degrees) => translate(degrees, Hand::Left, self),
-            R(degrees) => translate(degrees, Hand::Right, self),
-            F(val) => {
-                self.x += self.waypoint.rel_x * val;
-                self.y += self.waypoint.rel_y * val;
-            }
-        }
+    const INPUT: &str = "F10\nN3\nF7\nR90\nF11";
 
-        Ok(())
+    fn instructions(input: &str) -> impl Iterator<Item = Result<Instruction, Error>> + Clone + '_ {
+        input.lines().map(|line| line.parse::<Instruction>())
     }
-}
 
-fn plot_course(
-    instructions: impl Iterator<Item = Result<Instruction, Error>>,
-) -> Result<u32, Error> {
-    let mut boat = Boat {
-        x: 0,
-        y: 0,
-        direction: Direction::E,
-    };
-    for instruction in instructions {
-        boat.apply(instruction?)?;
+    #[test]
+    fn it_works() {
+        assert_eq!(
+            plot_course(&navigation::Heading, instructions(INPUT))
+                .unwrap()
+                .distance(),
+            25
+        );
+        assert_eq!(
+            plot_course(&navigation::Waypoint, instructions(INPUT))
+                .unwrap()
+                .distance(),
+            286
+        );
     }
 
-    Ok((boat.x.abs() + boat.y.abs()) as u32)
-}
+    #[test]
+    fn it_records_the_track() {
+        let track = plot_course(&navigation::Waypoint, instructions(INPUT)).unwrap();
+        let positions: Vec<(i32, i32)> = track.points.iter().map(|p| (p.x, p.y)).collect();
+        assert_eq!(
+            positions,
+            vec![
+                (0, 0),
+                (100, 10),
+                (100, 10),
+                (170, 38),
+                (170, 38),
+                (214, -72)
+            ]
+        );
+        assert_eq!(track.points[4].waypoint, Some((4, -10)));
 
-fn plot_course_with_waypoint(
-    instructions: impl Iterator<Item = Result<Instruction, Error>>,
-) -> Result<u32, Error> {
-    let mut boat = BoatWithWaypoint {
-        x: 0,
-        y: 0,
-        direction: Direction::E,
-        waypoint: Waypoint {
-            rel_x: 10,
-            rel_y: 1,
-        },
-    };
+        let csv = plot_course(&navigation::Heading, instructions(INPUT))
+            .unwrap()
+            .to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "step,instruction,x,y,heading,waypoint_x,waypoint_y"
+        );
+        assert_eq!(lines[5], "4,R90,17,3,S,,");
+        assert_eq!(lines.len(), 7);
 
-    for instruction in instructions {
-        boat.apply(instruction?)?;
+        let geojson = track.to_geojson();
+        assert!(geojson
+            .contains(r#""coordinates":[[0,0],[100,10],[100,10],[170,38],[170,38],[214,-72]]"#));
+        assert!(geojson.contains(r#""name":"waypoint""#));
     }
 
-    Ok((boat.x.abs() + boat.y.abs()) as u32)
-}
+    #[test]
+    fn it_rejects_non_right_angles() {
+        assert_eq!(
+            plot_course(&navigation::Heading, instructions("F10\nR45")),
+            Err(Error::NonRightAngle(45))
+        );
+        assert_eq!(
+            plot_course(&navigation::Waypoint, instructions("L100")),
+            Err(Error::NonRightAngle(100))
+        );
+    }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let input = std::fs::read_to_string("input.txt")?;
-    let instructions = input.lines().map(|line| line.parse::<Instruction>());
-    let part1 = plot_course(instructions.clone())?;
-    let part2 = plot_course_with_waypoint(instructions)?;
-    println!("part1: {}", part1);
-    println!("part1: {}", part2);
-    Ok(())
+    #[test]
+    fn it_can_carry_the_waypoint() {
+        let track = plot_course(&navigation::CarriedWaypoint, instructions(INPUT)).unwrap();
+        let end = track.points.last().unwrap();
+        // N3 moves the ship instead of the waypoint, so F7 goes 70 east.
+        assert_eq!((end.x, end.y), (100 + 70 + 11, 10 + 3 + 7 - 110));
+        assert_eq!(end.waypoint, Some((1, -10)));
+    }
 }
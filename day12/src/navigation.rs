@@ -0,0 +1,158 @@
+use crate::{quarter_turns, Direction, Error, Hand, Instruction};
+
+// Everything any mode needs to know about the ship. The waypoint is relative
+// to the ship, so it always travels along when the ship moves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ship {
+    pub x: i32,
+    pub y: i32,
+    pub heading: Direction,
+    pub waypoint: (i32, i32),
+}
+
+impl Default for Ship {
+    fn default() -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            heading: Direction::E,
+            waypoint: (10, 1),
+        }
+    }
+}
+
+impl Ship {
+    pub fn sail(&mut self, direction: Direction, val: i32) {
+        let (dx, dy) = direction.offset();
+        self.x += dx * val;
+        self.y += dy * val;
+    }
+
+    pub fn turn(&mut self, hand: Hand, degrees: i32) -> Result<(), Error> {
+        self.heading = self.heading.turn(degrees, hand)?;
+        Ok(())
+    }
+
+    pub fn move_waypoint(&mut self, direction: Direction, val: i32) {
+        let (dx, dy) = direction.offset();
+        self.waypoint.0 += dx * val;
+        self.waypoint.1 += dy * val;
+    }
+
+    pub fn rotate_waypoint(&mut self, hand: Hand, degrees: i32) -> Result<(), Error> {
+        let (x, y) = self.waypoint;
+        let quarters = match hand {
+            Hand::Left => -quarter_turns(degrees)?,
+            Hand::Right => quarter_turns(degrees)?,
+        };
+        self.waypoint = match quarters.rem_euclid(4) {
+            0 => (x, y),
+            1 => (y, -x),
+            2 => (-x, -y),
+            3 => (-y, x),
+            _ => unreachable!(),
+        };
+        Ok(())
+    }
+
+    pub fn towards_waypoint(&mut self, times: i32) {
+        self.x += self.waypoint.0 * times;
+        self.y += self.waypoint.1 * times;
+    }
+}
+
+// What an instruction means, before a mode decides what it does.
+pub enum Action {
+    Move(Direction, i32),
+    Turn(Hand, i32),
+    Forward(i32),
+}
+
+impl Instruction {
+    pub fn action(&self) -> Action {
+        use Instruction::*;
+
+        match *self {
+            N(val) => Action::Move(Direction::N, val),
+            E(val) => Action::Move(Direction::E, val),
+            S(val) => Action::Move(Direction::S, val),
+            W(val) => Action::Move(Direction::W, val),
+            L(degrees) => Action::Turn(Hand::Left, degrees),
+            R(degrees) => Action::Turn(Hand::Right, degrees),
+            F(val) => Action::Forward(val),
+        }
+    }
+}
+
+// One reading of the N/E/S/W/L/R/F language.
+pub trait NavigationMode {
+    fn apply(&self, ship: &mut Ship, instruction: Instruction) -> Result<(), Error>;
+
+    // Whether the waypoint means anything, rather than the heading.
+    fn uses_waypoint(&self) -> bool;
+}
+
+// Part 1: everything moves or turns the ship itself.
+pub struct Heading;
+
+impl NavigationMode for Heading {
+    fn apply(&self, ship: &mut Ship, instruction: Instruction) -> Result<(), Error> {
+        match instruction.action() {
+            Action::Move(direction, val) => ship.sail(direction, val),
+            Action::Turn(hand, degrees) => ship.turn(hand, degrees)?,
+            Action::Forward(val) => ship.sail(ship.heading, val),
+        }
+        Ok(())
+    }
+
+    fn uses_waypoint(&self) -> bool {
+        false
+    }
+}
+
+// Part 2: N/E/S/W and turns act on the waypoint, and F sails towards it.
+pub struct Waypoint;
+
+impl NavigationMode for Waypoint {
+    fn apply(&self, ship: &mut Ship, instruction: Instruction) -> Result<(), Error> {
+        match instruction.action() {
+            Action::Move(direction, val) => ship.move_waypoint(direction, val),
+            Action::Turn(hand, degrees) => ship.rotate_waypoint(hand, degrees)?,
+            Action::Forward(val) => ship.towards_waypoint(val),
+        }
+        Ok(())
+    }
+
+    fn uses_waypoint(&self) -> bool {
+        true
+    }
+}
+
+// N/E/S/W sail the ship and carry the waypoint along with it, while turns
+// and F still work on the waypoint.
+pub struct CarriedWaypoint;
+
+impl NavigationMode for CarriedWaypoint {
+    fn apply(&self, ship: &mut Ship, instruction: Instruction) -> Result<(), Error> {
+        match instruction.action() {
+            Action::Move(direction, val) => ship.sail(direction, val),
+            Action::Turn(hand, degrees) => ship.rotate_waypoint(hand, degrees)?,
+            Action::Forward(val) => ship.towards_waypoint(val),
+        }
+        Ok(())
+    }
+
+    fn uses_waypoint(&self) -> bool {
+        true
+    }
+}
+
+// `heading`, `waypoint` or `carried`.
+pub fn mode(name: &str) -> Option<Box<dyn NavigationMode>> {
+    match name {
+        "heading" => Some(Box::new(Heading)),
+        "waypoint" => Some(Box::new(Waypoint)),
+        "carried" => Some(Box::new(CarriedWaypoint)),
+        _ => None,
+    }
+}
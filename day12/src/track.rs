@@ -0,0 +1,100 @@
+use crate::{Direction, Instruction};
+
+// Where the ship was after one instruction. Step 0 is the start, before any
+// instruction. Heading-based ships have no waypoint, and the waypoint ship's
+// own heading never matters, so each point only has one of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackPoint {
+    pub step: usize,
+    pub instruction: Option<String>,
+    pub x: i32,
+    pub y: i32,
+    pub heading: Option<Direction>,
+    pub waypoint: Option<(i32, i32)>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Track {
+    pub points: Vec<TrackPoint>,
+}
+
+impl Track {
+    pub fn record(
+        &mut self,
+        instruction: Option<&Instruction>,
+        (x, y): (i32, i32),
+        heading: Option<Direction>,
+        waypoint: Option<(i32, i32)>,
+    ) {
+        self.points.push(TrackPoint {
+            step: self.points.len(),
+            instruction: instruction.map(Instruction::to_string),
+            x,
+            y,
+            heading,
+            waypoint,
+        });
+    }
+
+    // Manhattan distance from the start to where the ship ended up.
+    pub fn distance(&self) -> u32 {
+        match (self.points.first(), self.points.last()) {
+            (Some(start), Some(end)) => ((end.x - start.x).abs() + (end.y - start.y).abs()) as u32,
+            _ => 0,
+        }
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("step,instruction,x,y,heading,waypoint_x,waypoint_y\n");
+        for point in &self.points {
+            let (waypoint_x, waypoint_y) = match point.waypoint {
+                Some((x, y)) => (x.to_string(), y.to_string()),
+                None => (String::new(), String::new()),
+            };
+            csv.push_str(&format!(
+                "{},{},{},{},{},{},{}\n",
+                point.step,
+                point.instruction.as_deref().unwrap_or_default(),
+                point.x,
+                point.y,
+                point.heading.map(|h| h.to_string()).unwrap_or_default(),
+                waypoint_x,
+                waypoint_y
+            ));
+        }
+        csv
+    }
+
+    // A GeoJSON-style feature collection on a flat grid: the ship's path as
+    // one line string, plus one for each waypoint's absolute position.
+    pub fn to_geojson(&self) -> String {
+        let line = |coordinates: Vec<(i32, i32)>, name: &str| {
+            let coordinates: Vec<String> = coordinates
+                .iter()
+                .map(|(x, y)| format!("[{},{}]", x, y))
+                .collect();
+            format!(
+                r#"{{"type":"Feature","properties":{{"name":"{}"}},"geometry":{{"type":"LineString","coordinates":[{}]}}}}"#,
+                name,
+                coordinates.join(",")
+            )
+        };
+
+        let mut features = vec![line(
+            self.points.iter().map(|point| (point.x, point.y)).collect(),
+            "ship",
+        )];
+        let waypoints: Vec<(i32, i32)> = self
+            .points
+            .iter()
+            .filter_map(|point| point.waypoint.map(|(x, y)| (point.x + x, point.y + y)))
+            .collect();
+        if !waypoints.is_empty() {
+            features.push(line(waypoints, "waypoint"));
+        }
+        format!(
+            r#"{{"type":"FeatureCollection","features":[{}]}}"#,
+            features.join(",")
+        )
+    }
+}
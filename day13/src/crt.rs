@@ -0,0 +1,172 @@
+// Chinese remainder theorem for any moduli, coprime or not.
+// https://en.wikipedia.org/wiki/Chinese_remainder_theorem#Generalization_to_non-coprime_moduli
+// Everything is done in i128 with checked arithmetic, so i64 inputs can't
+// overflow silently.
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    NonPositiveModulus(i128),
+    // No x satisfies both of these.
+    Inconsistent(Congruence, Congruence),
+    Overflow,
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::NonPositiveModulus(modulus) => {
+                write!(f, "modulus must be positive, not {}", modulus)
+            }
+            Error::Inconsistent(a, b) => write!(f, "{} and {} have no common solution", a, b),
+            Error::Overflow => write!(f, "combined modulus is too large"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// x ≡ residue (mod modulus), with 0 <= residue < modulus.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Congruence {
+    pub residue: i128,
+    pub modulus: i128,
+}
+
+impl std::fmt::Display for Congruence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "x ≡ {} (mod {})", self.residue, self.modulus)
+    }
+}
+
+impl Congruence {
+    pub fn new(residue: i128, modulus: i128) -> Result<Self, Error> {
+        if modulus <= 0 {
+            return Err(Error::NonPositiveModulus(modulus));
+        }
+        Ok(Self {
+            residue: residue.rem_euclid(modulus),
+            modulus,
+        })
+    }
+
+    // The congruence satisfied by exactly the x that satisfy both.
+    pub fn combine(&self, other: &Congruence) -> Result<Congruence, Error> {
+        let (g, _, _) = extended_gcd(self.modulus, other.modulus);
+        let diff = other.residue - self.residue;
+        if diff % g != 0 {
+            return Err(Error::Inconsistent(*self, *other));
+        }
+
+        // self.residue + self.modulus * t ≡ other.residue (mod other.modulus),
+        // which after dividing through by g has a unique t mod other.modulus / g.
+        let step = other.modulus / g;
+        let inverse = inv_mod(self.modulus / g, step).expect("coprime after dividing by gcd");
+        let t = (diff / g)
+            .rem_euclid(step)
+            .checked_mul(inverse)
+            .ok_or(Error::Overflow)?
+            .rem_euclid(step);
+        let modulus = (self.modulus / g)
+            .checked_mul(other.modulus)
+            .ok_or(Error::Overflow)?;
+        let residue = self
+            .modulus
+            .checked_mul(t)
+            .and_then(|offset| offset.checked_add(self.residue))
+            .ok_or(Error::Overflow)?;
+        Congruence::new(residue, modulus)
+    }
+}
+
+// (g, x, y) with a * x + b * y = g = gcd(a, b).
+pub fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_x, mut x) = (1, 0);
+    let (mut old_y, mut y) = (0, 1);
+    while r != 0 {
+        let q = old_r / r;
+        let (next_r, next_x, next_y) = (old_r - q * r, old_x - q * x, old_y - q * y);
+        old_r = r;
+        r = next_r;
+        old_x = x;
+        x = next_x;
+        old_y = y;
+        y = next_y;
+    }
+    if old_r < 0 {
+        (-old_r, -old_x, -old_y)
+    } else {
+        (old_r, old_x, old_y)
+    }
+}
+
+// The inverse of x mod m, if x and m are coprime.
+pub fn inv_mod(x: i128, m: i128) -> Option<i128> {
+    let (g, inverse, _) = extended_gcd(x.rem_euclid(m), m);
+    if g != 1 {
+        return None;
+    }
+    Some(inverse.rem_euclid(m))
+}
+
+// Every x satisfying all the congruences, or `x ≡ 0 (mod 1)` if there are
+// none to satisfy.
+pub fn solve(congruences: impl IntoIterator<Item = Congruence>) -> Result<Congruence, Error> {
+    congruences
+        .into_iter()
+        .try_fold(Congruence::new(0, 1)?, |solution, congruence| {
+            solution.combine(&congruence)
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn congruence(residue: i128, modulus: i128) -> Congruence {
+        Congruence::new(residue, modulus).unwrap()
+    }
+
+    #[test]
+    fn can_find_modulo_inverse() {
+        assert_eq!(inv_mod(3, 13), Some(9));
+        assert_eq!(inv_mod(-3, 13), Some(4));
+        assert_eq!(inv_mod(4, 6), None);
+        assert_eq!(extended_gcd(240, 46), (2, -9, 47));
+    }
+
+    #[test]
+    fn it_solves_coprime_moduli() {
+        let solution = solve(vec![congruence(2, 3), congruence(3, 5), congruence(2, 7)]);
+        assert_eq!(solution, Ok(congruence(23, 105)));
+    }
+
+    #[test]
+    fn it_solves_non_coprime_moduli() {
+        assert_eq!(
+            solve(vec![congruence(2, 6), congruence(8, 10)]),
+            Ok(congruence(8, 30))
+        );
+        assert_eq!(
+            solve(vec![congruence(1, 6), congruence(2, 4)]),
+            Err(Error::Inconsistent(congruence(1, 6), congruence(2, 4)))
+        );
+        assert_eq!(solve(vec![]), Ok(congruence(0, 1)));
+        assert_eq!(Congruence::new(1, 0), Err(Error::NonPositiveModulus(0)));
+    }
+
+    #[test]
+    fn it_handles_large_moduli() {
+        // Two primes near 2^61, whose product doesn't fit in an i64.
+        let (p, q) = (2_305_843_009_213_693_951, 2_305_843_009_213_693_921);
+        let x = 1_234_567_890_123_456_789_012_345_i128;
+        let solution = solve(vec![congruence(x, p), congruence(x, q)]).unwrap();
+        assert_eq!(solution, congruence(x, p * q));
+
+        let huge = i128::MAX / 2;
+        assert_eq!(
+            solve(vec![congruence(0, huge), congruence(1, huge - 2)]),
+            Err(Error::Overflow)
+        );
+    }
+}
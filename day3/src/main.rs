@@ -1,61 +1,327 @@
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+#[derive(Debug)]
+enum Error {
+    EmptyMap,
+    UnevenRow(usize),
+    BadSlope(String),
+    BadRange(String),
+    UnknownFlag(String),
+    MissingFlagValue(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::EmptyMap => write!(f, "map is empty"),
+            Error::UnevenRow(y) => write!(f, "row {} is not as wide as the first row", y),
+            Error::BadSlope(s) => write!(f, "couldn't parse slope {:?}, expected RIGHT,DOWN", s),
+            Error::BadRange(s) => write!(f, "couldn't parse range {:?}, expected START..=END", s),
+            Error::UnknownFlag(flag) => write!(f, "unknown flag {}", flag),
+            Error::MissingFlagValue(flag) => write!(f, "missing value for {}", flag),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Slope {
+    right: isize,
+    down: usize,
+}
+
+impl Slope {
+    const PART2: [Slope; 5] = [
+        Slope { right: 1, down: 1 },
+        Slope { right: 3, down: 1 },
+        Slope { right: 5, down: 1 },
+        Slope { right: 7, down: 1 },
+        Slope { right: 1, down: 2 },
+    ];
+
+    fn new(right: isize, down: usize) -> Self {
+        Self { right, down }
+    }
+}
+
+// "3,1" => right 3, down 1. Right may be negative to travel leftwards.
+impl FromStr for Slope {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || Error::BadSlope(s.to_string());
+        let mut parts = s.split(',');
+        let right = parts
+            .next()
+            .and_then(|part| part.trim().parse::<isize>().ok())
+            .ok_or_else(err)?;
+        let down = parts
+            .next()
+            .and_then(|part| part.trim().parse::<usize>().ok())
+            .ok_or_else(err)?;
+        if down == 0 || parts.next().is_some() {
+            return Err(err());
+        }
+
+        Ok(Self::new(right, down))
+    }
+}
+
 struct Map {
     map: Vec<bool>,
     height: usize,
     width: usize,
 }
 
+impl FromStr for Map {
+    type Err = Error;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let width = input.lines().next().ok_or(Error::EmptyMap)?.chars().count();
+        let mut height = 0;
+        let mut map = Vec::with_capacity(input.len());
+
+        for (y, line) in input.lines().enumerate() {
+            if line.chars().count() != width {
+                return Err(Error::UnevenRow(y));
+            }
+            map.extend(line.chars().map(|c| c == '#'));
+            height += 1;
+        }
+
+        Ok(Self { map, height, width })
+    }
+}
+
 impl Map {
-    fn count_trees_on_journey(&self, horizontal_motion: usize, down: usize) -> usize {
-        let mut tree_count = 0;
-        let mut current_y = 0;
-
-        loop {
-            current_y += down;
-            if current_y > self.height { break }
-            let index = (horizontal_motion * current_y) % self.width + current_y * self.width;
-            match self.map.get(index) {
-                Some(true) => tree_count += 1,
-                None => break,
-                _ => continue,
+    fn is_tree(&self, (x, y): (usize, usize)) -> bool {
+        self.map[y * self.width + x]
+    }
+
+    // Every cell visited from the top left corner until we fall off the bottom
+    // of the map, including the starting cell.
+    fn path(&self, slope: Slope) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let width = self.width as isize;
+        let height = self.height;
+
+        std::iter::successors(Some((0, 0)), move |&(x, y): &(usize, usize)| {
+            if slope.down == 0 {
+                return None;
+            }
+            let y = y + slope.down;
+            if y >= height {
+                return None;
+            }
+            let x = (x as isize + slope.right).rem_euclid(width) as usize;
+            Some((x, y))
+        })
+    }
+
+    // The starting cell is where we set off from, so a tree there doesn't count.
+    fn count_trees_on_journey(&self, slope: Slope) -> usize {
+        self.path(slope)
+            .skip(1)
+            .filter(|&pos| self.is_tree(pos))
+            .count()
+    }
+
+    fn count_trees_for_slopes(&self, slopes: &[Slope]) -> Vec<usize> {
+        slopes
+            .iter()
+            .map(|&slope| self.count_trees_on_journey(slope))
+            .collect()
+    }
+
+    // Ties go to the first slope searched: smallest down first, then leftmost
+    // right.
+    fn fewest_trees(
+        &self,
+        rights: RangeInclusive<isize>,
+        downs: RangeInclusive<usize>,
+    ) -> Option<(Slope, usize)> {
+        downs
+            .filter(|&down| down > 0)
+            .flat_map(|down| rights.clone().map(move |right| Slope::new(right, down)))
+            .map(|slope| (slope, self.count_trees_on_journey(slope)))
+            .min_by_key(|&(_, count)| count)
+    }
+}
+
+// "-7..=7", or "3" for just that value. "-7..7" is rejected rather than
+// guessing whether the end is included.
+fn parse_range<T: FromStr>(s: &str) -> Result<RangeInclusive<T>, Error> {
+    let err = || Error::BadRange(s.to_string());
+    let (start, end) = match s.find("..") {
+        Some(i) => (&s[..i], s[i + 2..].strip_prefix('=').ok_or_else(err)?),
+        None => (s, s),
+    };
+    let start = start.trim().parse::<T>().map_err(|_| err())?;
+    let end = end.trim().parse::<T>().map_err(|_| err())?;
+    Ok(start..=end)
+}
+
+#[derive(Default)]
+struct Options {
+    slopes: Vec<Slope>,
+    paths: Vec<Slope>,
+    search: Option<(RangeInclusive<isize>, RangeInclusive<usize>)>,
+}
+
+impl Options {
+    const USAGE: &'static str =
+        "usage: day3 [--slope RIGHT,DOWN]... [--path RIGHT,DOWN]... [--search RIGHTS DOWNS]";
+
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, Error> {
+        let mut options = Self::default();
+
+        while let Some(flag) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| Error::MissingFlagValue(flag.clone()))
+            };
+            match flag.as_str() {
+                "--slope" => options.slopes.push(value()?.parse()?),
+                "--path" => options.paths.push(value()?.parse()?),
+                "--search" => {
+                    let rights = parse_range(&value()?)?;
+                    let downs = parse_range(&value()?)?;
+                    options.search = Some((rights, downs));
+                }
+                _ => return Err(Error::UnknownFlag(flag)),
             }
         }
-        tree_count
+
+        Ok(options)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.slopes.is_empty() && self.paths.is_empty() && self.search.is_none()
     }
 }
 
 fn part1(map: &Map) {
-    let tree_count = map.count_trees_on_journey(3, 1);
+    let tree_count = map.count_trees_on_journey(Slope::new(3, 1));
     println!("part1: {}", tree_count);
 }
 
 fn part2(map: &Map) {
-    let result = [
-        map.count_trees_on_journey(1, 1),
-        map.count_trees_on_journey(3, 1),
-        map.count_trees_on_journey(5, 1),
-        map.count_trees_on_journey(7, 1),
-        map.count_trees_on_journey(1, 2),
-    ].iter().fold(1, std::ops::Mul::mul);
+    let result: usize = map.count_trees_for_slopes(&Slope::PART2).iter().product();
 
     println!("part1: {:?}", result);
-
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let options = Options::parse(std::env::args().skip(1)).inspect_err(|_| {
+        eprintln!("{}", Options::USAGE);
+    })?;
     let input = std::fs::read_to_string("input.txt")?;
-    let width = input.lines().next().expect("coudln't find first line").chars().count();
-    let height = input.lines().count();
-
-    let map : Vec<bool> = input.lines().flat_map(|line| {
-        line.chars().map(|c| c == '#')
-    }).collect();
-    let map = Map {
-        map,
-        width,
-        height,
-    };
+    let map: Map = input.parse()?;
+
+    if options.is_empty() {
+        part1(&map);
+        part2(&map);
+        return Ok(());
+    }
+
+    if !options.slopes.is_empty() {
+        let counts = map.count_trees_for_slopes(&options.slopes);
+        for (slope, count) in options.slopes.iter().zip(&counts) {
+            println!("slope {},{}: {}", slope.right, slope.down, count);
+        }
+        println!("product: {}", counts.iter().product::<usize>());
+    }
+
+    for &slope in &options.paths {
+        let path = map
+            .path(slope)
+            .map(|(x, y)| {
+                let cell = if map.is_tree((x, y)) { '#' } else { '.' };
+                format!("({},{}){}", x, y, cell)
+            })
+            .collect::<Vec<_>>();
+        println!("path {},{}: {}", slope.right, slope.down, path.join(" "));
+    }
+
+    if let Some((rights, downs)) = options.search {
+        match map.fewest_trees(rights, downs) {
+            Some((slope, count)) => {
+                println!(
+                    "fewest trees: {},{} with {}",
+                    slope.right, slope.down, count
+                )
+            }
+            None => println!("fewest trees: no slopes in range"),
+        }
+    }
 
-    part1(&map);
-    part2(&map);
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"..##.......
+#...#...#..
+.#....#..#.
+..#.#...#.#
+.#...##..#.
+..#.##.....
+.#.#.#....#
+.#........#
+#.##...#...
+#...##....#
+.#..#...#.#"#;
+
+    #[test]
+    fn it_works_for_part2_slopes() {
+        let map: Map = INPUT.parse().unwrap();
+        assert_eq!(
+            map.count_trees_for_slopes(&Slope::PART2),
+            vec![2, 7, 3, 4, 2]
+        );
+    }
+
+    #[test]
+    fn path_stops_at_last_row() {
+        let map: Map = INPUT.parse().unwrap();
+        let path = map.path(Slope::new(1, 2)).collect::<Vec<_>>();
+        assert_eq!(path, vec![(0, 0), (1, 2), (2, 4), (3, 6), (4, 8), (5, 10)]);
+    }
+
+    #[test]
+    fn starting_cell_is_not_counted() {
+        let map: Map = "#.\n.#".parse().unwrap();
+        assert_eq!(map.count_trees_on_journey(Slope::new(1, 1)), 1);
+        assert_eq!(map.path(Slope::new(1, 1)).next(), Some((0, 0)));
+    }
+
+    #[test]
+    fn leftward_slopes_wrap() {
+        let map: Map = INPUT.parse().unwrap();
+        let path = map.path(Slope::new(-3, 1)).take(3).collect::<Vec<_>>();
+        assert_eq!(path, vec![(0, 0), (8, 1), (5, 2)]);
+    }
+
+    #[test]
+    fn it_finds_fewest_trees() {
+        let map: Map = INPUT.parse().unwrap();
+        assert_eq!(map.fewest_trees(-3..=3, 1..=2), Some((Slope::new(2, 1), 1)));
+        // -3,2 0,2 and 2,2 all hit one tree; the leftmost is searched first.
+        assert_eq!(
+            map.fewest_trees(-3..=3, 2..=2),
+            Some((Slope::new(-3, 2), 1))
+        );
+    }
+
+    #[test]
+    fn it_parses_slopes_and_ranges() {
+        assert_eq!("-3,1".parse::<Slope>().unwrap(), Slope::new(-3, 1));
+        assert!("3,0".parse::<Slope>().is_err());
+        assert_eq!(parse_range::<isize>("-7..=7").unwrap(), -7..=7);
+        assert!(parse_range::<isize>("-7..7").is_err());
+        assert_eq!(parse_range::<isize>("3").unwrap(), 3..=3);
+    }
+}
@@ -0,0 +1,168 @@
+use crate::schema::{Report, Schema};
+use std::collections::HashMap;
+use std::io::BufRead;
+
+#[derive(Debug)]
+pub struct Candidate<'a> {
+    pub attrs: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> Candidate<'a> {
+    // Fields are separated by any whitespace, so tabs and CRLF line endings
+    // are fine. A stray token without a value is kept so it shows up as invalid.
+    pub fn parse(input: &'a str) -> Self {
+        let attrs = input
+            .split_whitespace()
+            .map(|key_value| {
+                let mut k_v = key_value.splitn(2, ':');
+                (
+                    k_v.next().unwrap_or_default(),
+                    k_v.next().unwrap_or_default(),
+                )
+            })
+            .collect();
+
+        Self { attrs }
+    }
+
+    pub fn evaluate(&self, schema: &Schema) -> bool {
+        schema.has_required(&self.attrs)
+    }
+
+    pub fn evaluate_and_validate(&self, schema: &Schema) -> bool {
+        schema.validate(&self.attrs)
+    }
+
+    pub fn report(&self, schema: &Schema) -> Report {
+        schema.report(&self.attrs)
+    }
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+// Lazily yields the passports in an in-memory batch, borrowing from it.
+pub struct Candidates<'a> {
+    rest: &'a str,
+}
+
+pub fn parse(input: &str) -> Candidates<'_> {
+    Candidates { rest: input }
+}
+
+impl<'a> Iterator for Candidates<'a> {
+    type Item = Candidate<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut start = None;
+        let mut offset = 0;
+
+        for line in self.rest.split_inclusive('\n') {
+            let line_start = offset;
+            offset += line.len();
+            match (start, is_blank(line)) {
+                (None, true) => continue,
+                (None, false) => start = Some(line_start),
+                (Some(start), true) => {
+                    let passport = &self.rest[start..line_start];
+                    self.rest = &self.rest[offset..];
+                    return Some(Candidate::parse(passport));
+                }
+                (Some(_), false) => continue,
+            }
+        }
+
+        let passport = &self.rest[start?..];
+        self.rest = "";
+        Some(Candidate::parse(passport))
+    }
+}
+
+// Reads passports one at a time from a batch file, reusing a single buffer so
+// memory stays bounded by the largest passport rather than the whole batch.
+// Candidates borrow from that buffer, so this can't be an `Iterator`.
+pub struct PassportReader<R> {
+    reader: R,
+    buffer: String,
+}
+
+impl<R: BufRead> PassportReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: String::new(),
+        }
+    }
+
+    pub fn next_candidate(&mut self) -> Option<std::io::Result<Candidate<'_>>> {
+        self.buffer.clear();
+
+        loop {
+            let line_start = self.buffer.len();
+            match self.reader.read_line(&mut self.buffer) {
+                Err(e) => return Some(Err(e)),
+                Ok(0) => break,
+                Ok(_) if is_blank(&self.buffer[line_start..]) => {
+                    self.buffer.truncate(line_start);
+                    if !self.buffer.is_empty() {
+                        break;
+                    }
+                }
+                Ok(_) => continue,
+            }
+        }
+
+        parse(&self.buffer).next().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str =
+        "ecl:gry pid:860033327\r\neyr:2020\thcl:#fffffd\r\n\r\n\t\r\n\niyr:2013 ecl:amb\n \npid:1";
+
+    #[test]
+    fn it_splits_on_blank_lines_and_any_whitespace() {
+        let candidates = parse(INPUT).collect::<Vec<_>>();
+
+        assert_eq!(candidates.len(), 3);
+        assert_eq!(candidates[0].attrs.len(), 4);
+        assert_eq!(candidates[0].attrs["eyr"], "2020");
+        assert_eq!(candidates[0].attrs["hcl"], "#fffffd");
+        assert_eq!(candidates[1].attrs["ecl"], "amb");
+        assert_eq!(candidates[2].attrs["pid"], "1");
+    }
+
+    #[test]
+    fn reader_matches_in_memory_parser() {
+        let mut reader = PassportReader::new(INPUT.as_bytes());
+        let mut from_reader = vec![];
+        while let Some(candidate) = reader.next_candidate() {
+            let mut attrs = candidate
+                .unwrap()
+                .attrs
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<Vec<_>>();
+            attrs.sort();
+            from_reader.push(attrs);
+        }
+
+        let from_str = parse(INPUT)
+            .map(|candidate| {
+                let mut attrs = candidate
+                    .attrs
+                    .into_iter()
+                    .map(|(k, v)| (k.to_string(), v.to_string()))
+                    .collect::<Vec<_>>();
+                attrs.sort();
+                attrs
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(from_reader, from_str);
+    }
+}
@@ -0,0 +1,334 @@
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Deserialize)]
+pub struct Schema {
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Field {
+    pub key: String,
+    #[serde(default = "required_by_default")]
+    pub required: bool,
+    pub rule: Option<Rule>,
+}
+
+fn required_by_default() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Rule {
+    Year { min: u32, max: u32 },
+    Height { units: BTreeMap<String, Bounds> },
+    HexColor,
+    OneOf { values: Vec<String> },
+    Digits { len: usize },
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Bounds {
+    pub min: u32,
+    pub max: u32,
+}
+
+// Why a present value failed its rule.
+#[derive(Debug, PartialEq)]
+pub enum Reason {
+    NotANumber,
+    OutOfRange { min: u32, max: u32 },
+    UnknownUnit { units: Vec<String> },
+    NotAHexColor,
+    NotOneOf { values: Vec<String> },
+    WrongDigits { len: usize },
+}
+
+impl std::fmt::Display for Reason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Reason::NotANumber => write!(f, "is not a number"),
+            Reason::OutOfRange { min, max } => write!(f, "outside {}..={}", min, max),
+            Reason::UnknownUnit { units } => write!(f, "has no unit of {}", units.join(", ")),
+            Reason::NotAHexColor => write!(f, "is not # followed by six hex digits"),
+            Reason::NotOneOf { values } => write!(f, "is not one of {}", values.join(", ")),
+            Reason::WrongDigits { len } => write!(f, "is not exactly {} digits", len),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct InvalidField {
+    pub key: String,
+    pub value: String,
+    pub reason: Reason,
+}
+
+impl std::fmt::Display for InvalidField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {:?} {}", self.key, self.value, self.reason)
+    }
+}
+
+// Everything wrong with a single passport, in schema order.
+#[derive(Debug, Default, PartialEq)]
+pub struct Report {
+    pub missing: Vec<String>,
+    pub invalid: Vec<InvalidField>,
+}
+
+impl Report {
+    pub fn is_valid(&self) -> bool {
+        self.missing.is_empty() && self.invalid.is_empty()
+    }
+}
+
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut reasons = vec![];
+        if !self.missing.is_empty() {
+            reasons.push(format!("missing {}", self.missing.join(", ")));
+        }
+        reasons.extend(self.invalid.iter().map(|invalid| invalid.to_string()));
+        write!(f, "{}", reasons.join("; "))
+    }
+}
+
+impl Bounds {
+    fn check(&self, value: u32) -> Result<(), Reason> {
+        if (self.min..=self.max).contains(&value) {
+            Ok(())
+        } else {
+            Err(Reason::OutOfRange {
+                min: self.min,
+                max: self.max,
+            })
+        }
+    }
+}
+
+impl Rule {
+    pub fn check(&self, input: &str) -> Result<(), Reason> {
+        match self {
+            Rule::Year { min, max } => {
+                let year = input.parse::<u32>().map_err(|_| Reason::NotANumber)?;
+                Bounds {
+                    min: *min,
+                    max: *max,
+                }
+                .check(year)
+            }
+            Rule::Height { units } => {
+                let split = input
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(input.len());
+                let (digits, unit) = input.split_at(split);
+                let bounds = units.get(unit).ok_or_else(|| Reason::UnknownUnit {
+                    units: units.keys().cloned().collect(),
+                })?;
+                let height = digits.parse::<u32>().map_err(|_| Reason::NotANumber)?;
+                bounds.check(height)
+            }
+            Rule::HexColor => match input.strip_prefix('#') {
+                Some(hex)
+                    if hex.len() == 6
+                        && hex
+                            .chars()
+                            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)) =>
+                {
+                    Ok(())
+                }
+                _ => Err(Reason::NotAHexColor),
+            },
+            Rule::OneOf { values } => {
+                if values.iter().any(|value| value == input) {
+                    Ok(())
+                } else {
+                    Err(Reason::NotOneOf {
+                        values: values.clone(),
+                    })
+                }
+            }
+            Rule::Digits { len } => {
+                if input.len() == *len && input.chars().all(|c| c.is_ascii_digit()) {
+                    Ok(())
+                } else {
+                    Err(Reason::WrongDigits { len: *len })
+                }
+            }
+        }
+    }
+}
+
+impl Field {
+    pub fn check(&self, input: &str) -> Result<(), Reason> {
+        match &self.rule {
+            Some(rule) => rule.check(input),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Schema {
+    pub fn has_required(&self, attrs: &HashMap<&str, &str>) -> bool {
+        self.fields
+            .iter()
+            .filter(|field| field.required)
+            .all(|field| attrs.contains_key(field.key.as_str()))
+    }
+
+    pub fn report(&self, attrs: &HashMap<&str, &str>) -> Report {
+        let mut report = Report::default();
+
+        for field in &self.fields {
+            match attrs.get(field.key.as_str()) {
+                Some(value) => {
+                    if let Err(reason) = field.check(value) {
+                        report.invalid.push(InvalidField {
+                            key: field.key.clone(),
+                            value: value.to_string(),
+                            reason,
+                        });
+                    }
+                }
+                None if field.required => report.missing.push(field.key.clone()),
+                None => {}
+            }
+        }
+
+        report
+    }
+
+    pub fn validate(&self, attrs: &HashMap<&str, &str>) -> bool {
+        self.report(attrs).is_valid()
+    }
+}
+
+impl std::str::FromStr for Schema {
+    type Err = toml::de::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        toml::from_str(s)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn schema() -> Schema {
+        include_str!("../schema.toml").parse().unwrap()
+    }
+
+    fn field(key: &str) -> Field {
+        let schema = schema();
+        schema
+            .fields
+            .into_iter()
+            .find(|field| field.key == key)
+            .unwrap()
+    }
+
+    fn validate(key: &str, input: &str) -> bool {
+        field(key).check(input).is_ok()
+    }
+
+    #[test]
+    fn byr_works() {
+        assert!(validate("byr", "2002"));
+        assert!(!validate("byr", "2003"));
+    }
+
+    #[test]
+    fn iyr_works() {
+        assert!(validate("iyr", "2020"));
+        assert!(!validate("iyr", "2021"));
+    }
+
+    #[test]
+    fn eyr_works() {
+        assert!(validate("eyr", "2030"));
+        assert!(!validate("eyr", "2031"));
+    }
+
+    #[test]
+    fn hgt_works() {
+        assert!(validate("hgt", "60in"));
+        assert!(validate("hgt", "190cm"));
+        assert!(!validate("hgt", "190in"));
+        assert!(!validate("hgt", "190"));
+        assert!(!validate("hgt", "cm"));
+    }
+
+    #[test]
+    fn ecl_works() {
+        assert!(validate("ecl", "brn"));
+        assert!(!validate("ecl", "wat"));
+    }
+
+    #[test]
+    fn pid_works() {
+        assert!(validate("pid", "123456789"));
+        assert!(validate("pid", "000000091"));
+        assert!(!validate("pid", "abcdef09g"));
+    }
+
+    #[test]
+    fn hcl_works() {
+        assert!(validate("hcl", "#123456"));
+        assert!(validate("hcl", "#fffffa"));
+        assert!(!validate("hcl", "abcdef09g"));
+        assert!(!validate("hcl", "#abcd9g"));
+        assert!(validate("hcl", "#a97842"));
+    }
+
+    #[test]
+    fn cid_is_optional_and_unchecked() {
+        let cid = field("cid");
+        assert!(!cid.required);
+        assert!(cid.check("anything").is_ok());
+    }
+
+    #[test]
+    fn it_reports_missing_and_invalid_fields() {
+        let attrs = [
+            ("hgt", "190in"),
+            ("ecl", "wat"),
+            ("pid", "12"),
+            ("byr", "cm"),
+        ]
+        .iter()
+        .copied()
+        .collect();
+        let report = schema().report(&attrs);
+
+        assert_eq!(report.missing, vec!["iyr", "eyr", "hcl"]);
+        assert_eq!(
+            report
+                .invalid
+                .iter()
+                .map(|invalid| invalid.to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "byr \"cm\" is not a number",
+                "hgt \"190in\" outside 59..=76",
+                "ecl \"wat\" is not one of amb, blu, brn, gry, grn, hzl, oth",
+                "pid \"12\" is not exactly 9 digits",
+            ]
+        );
+        assert!(!report.is_valid());
+    }
+
+    #[test]
+    fn it_reports_heights_without_digits() {
+        let hgt = field("hgt");
+        assert_eq!(hgt.check("cm"), Err(Reason::NotANumber));
+        assert_eq!(
+            hgt.check("60"),
+            Err(Reason::UnknownUnit {
+                units: vec!["cm".to_string(), "in".to_string()]
+            })
+        );
+    }
+}
@@ -1,75 +1,283 @@
+mod manifest;
+
+use manifest::Manifest;
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let input = std::fs::read_to_string("input.txt")?;
-    let mut all_seats = input
-        .lines()
-        .map(|line| {
-            let seat: Seat = line.into();
-            seat
-        })
-        .collect::<Vec<_>>();
-    let max = all_seats
-        .iter()
+    let manifest = Manifest::from_passes(Layout::default(), input.lines())?;
+    let max = manifest
+        .seats()
         .map(|seat| seat.id)
         .max()
-        .expect("couldn't find max");
+        .ok_or("no boarding passes")?;
     println!("part1: {}", max);
 
-    all_seats.sort_by(|a, b| a.id.cmp(&b.id));
-    let result = all_seats
-        .windows(2)
-        .find(|window| {
-            let mut iter = window.iter();
-            let a = iter.next().expect("can't find first seat");
-            let b = iter.next().expect("can't find second seat");
-            (b.id - a.id) == 2
-        })
-        .expect("couldn't find a window that matched");
+    let missing = manifest
+        .missing()
+        .iter()
+        .map(|seat| seat.id.to_string())
+        .collect::<Vec<_>>();
+    println!("part2: {}", missing.join(", "));
 
-    println!("part2: {}", result.iter().next().unwrap().id + 1);
+    for seat in manifest.duplicates() {
+        println!("duplicate: {}", Layout::default().encode(seat)?);
+    }
+
+    run_args(std::env::args().skip(1), &manifest)?;
     Ok(())
 }
 
-#[derive(Debug)]
-struct Seat {
-    row: i16,
-    column: i16,
-    id: i32,
-}
+// --layout ROW_BITS,COLUMN_BITS,MULTIPLIER applies to every later
+// --decode PASS and --encode ROW,COLUMN. --map prints the boarded seats.
+fn run_args(mut args: impl Iterator<Item = String>, manifest: &Manifest) -> Result<(), Error> {
+    fn numbers(value: &str, count: usize) -> Result<Vec<u32>, Error> {
+        let numbers = value
+            .split(',')
+            .map(|n| n.trim().parse::<u32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| Error::BadArgument(value.to_string()))?;
+        if numbers.len() != count {
+            return Err(Error::BadArgument(value.to_string()));
+        }
+        Ok(numbers)
+    }
 
-impl From<&str> for Seat {
-    fn from(input: &str) -> Self {
-        fn collect_bits(mut memo: i16, c: char) -> i16 {
-            memo = memo << 1;
-            memo |= match c {
-                'F' | 'L' => 0,
-                'B' | 'R' => 1,
-                _ => panic!("unknown char to bit"),
-            };
-            memo
+    let mut layout = Layout::default();
+    while let Some(flag) = args.next() {
+        if flag == "--map" {
+            println!("{}", manifest.render());
+            continue;
+        }
+
+        let value = args
+            .next()
+            .ok_or_else(|| Error::BadArgument(flag.clone()))?;
+        match flag.as_str() {
+            "--layout" => {
+                let numbers = numbers(&value, 3)?;
+                layout = Layout {
+                    row_bits: numbers[0],
+                    column_bits: numbers[1],
+                    id_multiplier: numbers[2],
+                };
+            }
+            "--decode" => {
+                let seat = layout.decode(&value)?;
+                println!(
+                    "{}: row {}, column {}, id {}",
+                    value, seat.row, seat.column, seat.id
+                );
+            }
+            "--encode" => {
+                let numbers = numbers(&value, 2)?;
+                let seat = layout.seat(numbers[0], numbers[1])?;
+                println!("{}: {}", value, layout.encode(&seat)?);
+            }
+            _ => return Err(Error::BadArgument(flag)),
         }
+    }
+    Ok(())
+}
 
-        let row = input.chars().take(7).fold(0_i16, collect_bits);
+#[derive(Debug, PartialEq)]
+enum Error {
+    WrongLength { expected: usize, found: usize },
+    UnknownChar { position: usize, found: char },
+    OutOfRange { row: u32, column: u32 },
+    LayoutTooLarge,
+    BadArgument(String),
+}
 
-        let column = input.chars().skip(7).fold(0, collect_bits);
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for Error {}
+
+// How many bits of a boarding pass pick the row and column, and how the seat
+// id is derived from them. The puzzle plane is 7 row bits, 3 column bits and
+// `row * 8 + column`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Layout {
+    row_bits: u32,
+    column_bits: u32,
+    id_multiplier: u32,
+}
 
+impl Default for Layout {
+    fn default() -> Self {
         Self {
+            row_bits: 7,
+            column_bits: 3,
+            id_multiplier: 8,
+        }
+    }
+}
+
+impl Layout {
+    fn rows(&self) -> u32 {
+        1 << self.row_bits
+    }
+
+    fn columns(&self) -> u32 {
+        1 << self.column_bits
+    }
+
+    fn check(&self) -> Result<(), Error> {
+        if self
+            .row_bits
+            .checked_add(self.column_bits)
+            .is_none_or(|bits| bits >= u32::BITS)
+        {
+            return Err(Error::LayoutTooLarge);
+        }
+        self.id_multiplier
+            .checked_mul(self.rows())
+            .map(|_| ())
+            .ok_or(Error::LayoutTooLarge)
+    }
+
+    fn seat(&self, row: u32, column: u32) -> Result<Seat, Error> {
+        self.check()?;
+        if row >= self.rows() || column >= self.columns() {
+            return Err(Error::OutOfRange { row, column });
+        }
+
+        Ok(Seat {
             row,
             column,
-            id: row as i32 * 8 + column as i32,
+            id: row * self.id_multiplier + column,
+        })
+    }
+
+    fn decode(&self, input: &str) -> Result<Seat, Error> {
+        self.check()?;
+        let expected = (self.row_bits + self.column_bits) as usize;
+        let found = input.chars().count();
+        if found != expected {
+            return Err(Error::WrongLength { expected, found });
         }
+
+        let mut row = 0;
+        let mut column = 0;
+        for (position, c) in input.chars().enumerate() {
+            let is_row = position < self.row_bits as usize;
+            let bit = match (is_row, c) {
+                (true, 'F') | (false, 'L') => 0,
+                (true, 'B') | (false, 'R') => 1,
+                (_, found) => return Err(Error::UnknownChar { position, found }),
+            };
+            if is_row {
+                row = row << 1 | bit;
+            } else {
+                column = column << 1 | bit;
+            }
+        }
+
+        self.seat(row, column)
+    }
+
+    fn encode(&self, seat: &Seat) -> Result<String, Error> {
+        self.seat(seat.row, seat.column)?;
+
+        let row = (0..self.row_bits)
+            .rev()
+            .map(|bit| if seat.row >> bit & 1 == 1 { 'B' } else { 'F' });
+        let column = (0..self.column_bits).rev().map(|bit| {
+            if seat.column >> bit & 1 == 1 {
+                'R'
+            } else {
+                'L'
+            }
+        });
+
+        Ok(row.chain(column).collect())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Seat {
+    row: u32,
+    column: u32,
+    id: u32,
+}
+
+impl std::convert::TryFrom<&str> for Seat {
+    type Error = Error;
+
+    fn try_from(input: &str) -> Result<Self, Self::Error> {
+        Layout::default().decode(input)
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use std::convert::TryFrom;
 
     #[test]
     fn convert_string_to_seat() {
-        let seat: Seat = "BFFFBBFRRR".into();
+        let seat = Seat::try_from("BFFFBBFRRR").unwrap();
         // row 70, column 7, seat ID 567
         assert_eq!(seat.row, 70);
         assert_eq!(seat.column, 7);
         assert_eq!(seat.id, 567);
     }
+
+    #[test]
+    fn it_round_trips_boarding_passes() {
+        let layout = Layout::default();
+        for pass in &["BFFFBBFRRR", "FFFBBBFRRR", "BBFFBBFRLL", "FFFFFFFLLL"] {
+            let seat = Seat::try_from(*pass).unwrap();
+            assert_eq!(layout.encode(&seat).unwrap(), *pass);
+        }
+    }
+
+    #[test]
+    fn it_rejects_bad_boarding_passes() {
+        assert_eq!(
+            Seat::try_from("BFFFBBFRR"),
+            Err(Error::WrongLength {
+                expected: 10,
+                found: 9
+            })
+        );
+        assert_eq!(
+            Seat::try_from("BFFFBBFRRB"),
+            Err(Error::UnknownChar {
+                position: 9,
+                found: 'B'
+            })
+        );
+        assert_eq!(
+            Layout::default().seat(128, 0),
+            Err(Error::OutOfRange {
+                row: 128,
+                column: 0
+            })
+        );
+    }
+
+    #[test]
+    fn it_supports_other_layouts() {
+        let layout = Layout {
+            row_bits: 5,
+            column_bits: 2,
+            id_multiplier: 10,
+        };
+        let seat = layout.decode("BFFBFRL").unwrap();
+        assert_eq!(seat.row, 18);
+        assert_eq!(seat.column, 2);
+        assert_eq!(seat.id, 182);
+        assert_eq!(layout.encode(&seat).unwrap(), "BFFBFRL");
+
+        let huge = Layout {
+            row_bits: u32::MAX,
+            column_bits: 1,
+            id_multiplier: 8,
+        };
+        assert_eq!(huge.decode("F"), Err(Error::LayoutTooLarge));
+    }
 }
@@ -0,0 +1,166 @@
+use crate::{Error, Layout, Seat};
+
+// Which seats of a plane have been boarded, one bit per seat in row-major
+// order, plus any boarding passes that were scanned more than once.
+pub struct Manifest {
+    layout: Layout,
+    occupied: Vec<u64>,
+    duplicates: Vec<Seat>,
+}
+
+impl Manifest {
+    pub fn new(layout: Layout) -> Result<Self, Error> {
+        layout.check()?;
+        let seats = (layout.rows() * layout.columns()) as usize;
+        Ok(Self {
+            layout,
+            occupied: vec![0; seats.div_ceil(64)],
+            duplicates: vec![],
+        })
+    }
+
+    pub fn from_passes<'a>(
+        layout: Layout,
+        passes: impl Iterator<Item = &'a str>,
+    ) -> Result<Self, Error> {
+        let mut manifest = Self::new(layout)?;
+        for pass in passes {
+            manifest.board(layout.decode(pass)?);
+        }
+        Ok(manifest)
+    }
+
+    fn index(&self, row: u32, column: u32) -> usize {
+        (row * self.layout.columns() + column) as usize
+    }
+
+    fn is_occupied(&self, index: usize) -> bool {
+        self.occupied[index / 64] >> (index % 64) & 1 == 1
+    }
+
+    // Returns false, and remembers the seat, if it was already boarded.
+    pub fn board(&mut self, seat: Seat) -> bool {
+        let index = self.index(seat.row, seat.column);
+        if self.is_occupied(index) {
+            self.duplicates.push(seat);
+            return false;
+        }
+        self.occupied[index / 64] |= 1 << (index % 64);
+        true
+    }
+
+    pub fn duplicates(&self) -> &[Seat] {
+        &self.duplicates
+    }
+
+    fn seat_at(&self, index: usize) -> Seat {
+        let columns = self.layout.columns() as usize;
+        self.layout
+            .seat((index / columns) as u32, (index % columns) as u32)
+            .expect("index is inside the layout")
+    }
+
+    fn len(&self) -> usize {
+        (self.layout.rows() * self.layout.columns()) as usize
+    }
+
+    pub fn seats(&self) -> impl Iterator<Item = Seat> + '_ {
+        (0..self.len())
+            .filter(move |&index| self.is_occupied(index))
+            .map(move |index| self.seat_at(index))
+    }
+
+    // Empty seats between the first and last boarded seat. The seats before
+    // and after those don't exist on this plane, so they're never reported.
+    fn missing_indices(&self) -> std::ops::Range<usize> {
+        let first = (0..self.len()).find(|&index| self.is_occupied(index));
+        let last = (0..self.len()).rev().find(|&index| self.is_occupied(index));
+        match (first, last) {
+            (Some(first), Some(last)) => first..last,
+            _ => 0..0,
+        }
+    }
+
+    pub fn missing(&self) -> Vec<Seat> {
+        self.missing_indices()
+            .filter(|&index| !self.is_occupied(index))
+            .map(|index| self.seat_at(index))
+            .collect()
+    }
+
+    // One line per row: `#` boarded, `x` missing, `.` outside the boarded range.
+    pub fn render(&self) -> String {
+        let columns = self.layout.columns() as usize;
+        let missing = self.missing_indices();
+        let width = self.layout.rows().to_string().len();
+
+        (0..self.layout.rows() as usize)
+            .map(|row| {
+                let seats = (row * columns..(row + 1) * columns)
+                    .map(|index| {
+                        if self.is_occupied(index) {
+                            '#'
+                        } else if missing.contains(&index) {
+                            'x'
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect::<String>();
+                format!("{:>width$} {}", row, seats, width = width)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn layout() -> Layout {
+        Layout {
+            row_bits: 2,
+            column_bits: 2,
+            id_multiplier: 4,
+        }
+    }
+
+    fn manifest(seats: &[(u32, u32)]) -> Manifest {
+        let layout = layout();
+        let passes = seats
+            .iter()
+            .map(|&(row, column)| layout.encode(&layout.seat(row, column).unwrap()).unwrap())
+            .collect::<Vec<_>>();
+        Manifest::from_passes(layout, passes.iter().map(String::as_str)).unwrap()
+    }
+
+    #[test]
+    fn it_finds_every_gap_but_not_the_front_or_back() {
+        let manifest = manifest(&[(1, 1), (1, 3), (2, 0), (2, 2)]);
+        let missing = manifest
+            .missing()
+            .iter()
+            .map(|seat| seat.id)
+            .collect::<Vec<_>>();
+
+        assert_eq!(missing, vec![6, 9]);
+        assert_eq!(manifest.render(), "0 ....\n1 .#x#\n2 #x#.\n3 ....");
+    }
+
+    #[test]
+    fn it_detects_duplicate_passes() {
+        let manifest = manifest(&[(1, 1), (1, 2), (1, 1)]);
+
+        assert_eq!(manifest.duplicates(), &[layout().seat(1, 1).unwrap()]);
+        assert_eq!(manifest.seats().count(), 2);
+        assert!(manifest.missing().is_empty());
+    }
+
+    #[test]
+    fn it_has_nothing_missing_when_empty() {
+        let manifest = Manifest::new(layout()).unwrap();
+        assert!(manifest.missing().is_empty());
+        assert_eq!(manifest.seats().count(), 0);
+    }
+}
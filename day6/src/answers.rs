@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    InvalidChar { line: usize, found: char },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for Error {}
+
+// Maps answer characters to bit indices. `a..=z` are always 0..26 so the
+// puzzle input stays in the `u32` fast path; any other printable character
+// gets the next free index the first time it's seen.
+#[derive(Debug)]
+pub struct Alphabet {
+    extra: HashMap<char, usize>,
+}
+
+impl Alphabet {
+    const LETTERS: usize = 26;
+
+    pub fn new() -> Self {
+        Self {
+            extra: HashMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        Self::LETTERS + self.extra.len()
+    }
+
+    fn index(&mut self, c: char) -> Option<usize> {
+        if c.is_ascii_lowercase() {
+            return Some((c as u8 - b'a') as usize);
+        }
+        if c.is_whitespace() || c.is_control() {
+            return None;
+        }
+        let next = self.len();
+        Some(*self.extra.entry(c).or_insert(next))
+    }
+
+    pub fn person(&mut self, line: &str, line_number: usize) -> Result<AnswerSet, Error> {
+        let mut answers = AnswerSet::empty();
+        for c in line.chars() {
+            let index = self.index(c).ok_or(Error::InvalidChar {
+                line: line_number,
+                found: c,
+            })?;
+            answers.insert(index);
+        }
+        Ok(answers)
+    }
+}
+
+// A set of answer indices. Sets that fit in 32 bits stay a plain mask; the
+// rest spill into a growable bitset of 64-bit words.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnswerSet {
+    Small(u32),
+    Large(Vec<u64>),
+}
+
+impl AnswerSet {
+    pub fn empty() -> Self {
+        AnswerSet::Small(0)
+    }
+
+    // Every index below `len`.
+    pub fn full(len: usize) -> Self {
+        if len <= 32 {
+            return AnswerSet::Small((1_u64 << len).wrapping_sub(1) as u32);
+        }
+        let mut words = vec![u64::MAX; len / 64];
+        if !len.is_multiple_of(64) {
+            words.push((1 << (len % 64)) - 1);
+        }
+        AnswerSet::Large(words)
+    }
+
+    fn words(&self) -> Vec<u64> {
+        match self {
+            AnswerSet::Small(mask) => vec![*mask as u64],
+            AnswerSet::Large(words) => words.clone(),
+        }
+    }
+
+    fn normalize(mut words: Vec<u64>) -> Self {
+        while words.len() > 1 && words.last() == Some(&0) {
+            words.pop();
+        }
+        match words.as_slice() {
+            [] => AnswerSet::Small(0),
+            [word] if *word <= u32::MAX as u64 => AnswerSet::Small(*word as u32),
+            _ => AnswerSet::Large(words),
+        }
+    }
+
+    pub fn insert(&mut self, index: usize) {
+        match self {
+            AnswerSet::Small(mask) if index < 32 => *mask |= 1 << index,
+            _ => {
+                let mut words = self.words();
+                if words.len() <= index / 64 {
+                    words.resize(index / 64 + 1, 0);
+                }
+                words[index / 64] |= 1 << (index % 64);
+                *self = AnswerSet::Large(words);
+            }
+        }
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        match self {
+            AnswerSet::Small(mask) => index < 32 && mask >> index & 1 == 1,
+            AnswerSet::Large(words) => words
+                .get(index / 64)
+                .is_some_and(|word| word >> (index % 64) & 1 == 1),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            AnswerSet::Small(mask) => mask.count_ones() as usize,
+            AnswerSet::Large(words) => words.iter().map(|word| word.count_ones() as usize).sum(),
+        }
+    }
+
+    // `f` must map (0, 0) to 0 so the shorter side can be padded with zeroes.
+    fn zip_with(&self, other: &Self, f: impl Fn(u64, u64) -> u64) -> Self {
+        if let (AnswerSet::Small(a), AnswerSet::Small(b)) = (self, other) {
+            return AnswerSet::Small(f(*a as u64, *b as u64) as u32);
+        }
+        let (a, b) = (self.words(), other.words());
+        let words = (0..a.len().max(b.len()))
+            .map(|i| {
+                f(
+                    a.get(i).copied().unwrap_or(0),
+                    b.get(i).copied().unwrap_or(0),
+                )
+            })
+            .collect();
+        Self::normalize(words)
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        self.zip_with(other, |a, b| a | b)
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        self.zip_with(other, |a, b| a & b)
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        self.zip_with(other, |a, b| a & !b)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn letters_stay_small() {
+        let mut alphabet = Alphabet::new();
+        let answers = alphabet.person("abz", 1).unwrap();
+        assert_eq!(answers, AnswerSet::Small(1 | 2 | 1 << 25));
+        assert_eq!(alphabet.len(), 26);
+    }
+
+    #[test]
+    fn other_characters_spill_into_large_sets() {
+        let mut alphabet = Alphabet::new();
+        let mut answers = alphabet.person("aé😀", 1).unwrap();
+        assert_eq!(alphabet.len(), 28);
+        assert_eq!(answers.len(), 3);
+        assert!(answers.contains(0) && answers.contains(26) && answers.contains(27));
+
+        answers.insert(100);
+        assert!(matches!(answers, AnswerSet::Large(_)));
+        assert!(answers.contains(100));
+        assert_eq!(
+            answers.intersection(&AnswerSet::full(26)),
+            AnswerSet::Small(1)
+        );
+        assert_eq!(AnswerSet::full(101).difference(&answers).len(), 97);
+    }
+
+    #[test]
+    fn it_rejects_invalid_characters() {
+        let mut alphabet = Alphabet::new();
+        assert_eq!(
+            alphabet.person("ab c", 3),
+            Err(Error::InvalidChar {
+                line: 3,
+                found: ' '
+            })
+        );
+        assert_eq!(
+            alphabet.person("ab\r", 4),
+            Err(Error::InvalidChar {
+                line: 4,
+                found: '\r'
+            })
+        );
+    }
+}
@@ -1,55 +1,70 @@
-#[derive(Debug)]
-struct Group {
-    count: usize,
-}
+mod answers;
+mod query;
+
+use answers::{Alphabet, AnswerSet};
+use query::Query;
 
-const A: u8 = 97;
+struct Groups {
+    groups: Vec<Vec<AnswerSet>>,
+    alphabet: Alphabet,
+}
 
-fn take_group2<'a>(input: &'a str) -> Group {
-    let count = input
-        .lines()
-        .fold(0b1111_1111_1111_1111_1111_1111_11 as u32, |mask, line| {
-            mask & line
-                .chars()
-                .fold(0, |bit_mask, c| bit_mask | 1 << (c as u8 - A))
-        })
-        .count_ones();
+// Groups are separated by blank lines, one person per line.
+fn parse(input: &str) -> Result<Groups, answers::Error> {
+    let mut alphabet = Alphabet::new();
+    let mut groups = vec![];
+    let mut group = vec![];
 
-    Group {
-        count: count as usize,
+    for (i, line) in input.lines().enumerate() {
+        if line.is_empty() {
+            if !group.is_empty() {
+                groups.push(std::mem::take(&mut group));
+            }
+            continue;
+        }
+        group.push(alphabet.person(line, i + 1)?);
+    }
+    if !group.is_empty() {
+        groups.push(group);
     }
-}
 
-fn take_group<'a>(input: &'a str) -> Group {
-    let count = input
-        .lines()
-        .flat_map(|line| line.chars())
-        .collect::<std::collections::HashSet<char>>()
-        .len();
+    Ok(Groups { groups, alphabet })
+}
 
-    Group { count }
+impl Groups {
+    fn sum(&self, query: &Query) -> usize {
+        self.groups
+            .iter()
+            .map(|group| query.eval(group, self.alphabet.len()).len())
+            .sum()
+    }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let input = std::fs::read_to_string("input.txt")?;
-    let groups = input.split("\n\n");
 
-    let part1: usize = groups.map(|g| take_group(g).count).sum();
+    let groups = parse(&input)?;
+
+    let part1: usize = groups.sum(&Query::Union);
     println!("part1: {}", part1);
 
-    let part2: usize = part2(&input);
+    let part2: usize = groups.sum(&Query::Intersect);
     println!("part2: {}", part2);
-    Ok(())
-}
 
-fn part2(input: &str) -> usize {
-    let groups = input.split("\n\n");
-    groups.map(|g| take_group2(g).count).sum()
+    for query in std::env::args().skip(1) {
+        println!("{}: {}", query, groups.sum(&query.parse()?));
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+
+    fn part2(input: &str) -> usize {
+        parse(input).unwrap().sum(&Query::Intersect)
+    }
+
     #[test]
     fn it_works() {
         let input = r#"abc
@@ -67,12 +82,18 @@ a
 a
 
 b"#;
-        assert_eq!(part2(&input), 6);
+        assert_eq!(part2(input), 6);
     }
 
     #[test]
     fn it_works_for_abc() {
         let input = "abc";
-        assert_eq!(part2(&input), 3);
+        assert_eq!(part2(input), 3);
+    }
+
+    #[test]
+    fn it_rejects_invalid_answers() {
+        assert!(parse("abc\n\nab c").is_err());
+        assert_eq!(parse("aé\néb").unwrap().sum(&Query::Intersect), 1);
     }
 }
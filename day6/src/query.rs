@@ -0,0 +1,263 @@
+// A small language for asking which questions a group answered, evaluated on
+// one answer set per person. For example:
+//
+//   union                      answered by anyone
+//   intersect                  answered by everyone
+//   at_least(2)                answered by two or more people
+//   at_least(50%)              answered by at least half the group
+//   exactly(1)                 answered by exactly one person
+//   not(union)                 in the alphabet but answered by nobody
+//   and(at_least(2), not(intersect)), or(...)
+use crate::answers::AnswerSet;
+use std::str::FromStr;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    UnexpectedEnd,
+    UnexpectedChar(usize, char),
+    UnknownFunction(String),
+    WrongArgumentCount(String),
+    BadCount(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Count {
+    People(usize),
+    Percent(usize),
+}
+
+impl Count {
+    // Percentages round up, so 50% of a group of three is two people.
+    fn people(&self, group_size: usize) -> usize {
+        match *self {
+            Count::People(people) => people,
+            Count::Percent(percent) => (group_size * percent).div_ceil(100),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Query {
+    Union,
+    Intersect,
+    AtLeast(Count),
+    Exactly(Count),
+    Not(Box<Query>),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+}
+
+impl Query {
+    // `alphabet` is how many distinct answers exist, which is what `not` and
+    // an empty `and` are relative to.
+    pub fn eval(&self, group: &[AnswerSet], alphabet: usize) -> AnswerSet {
+        match self {
+            Query::Union => group
+                .iter()
+                .fold(AnswerSet::empty(), |memo, person| memo.union(person)),
+            Query::Intersect => group
+                .iter()
+                .fold(AnswerSet::full(alphabet), |memo, person| {
+                    memo.intersection(person)
+                }),
+            Query::AtLeast(count) => {
+                let people = count.people(group.len());
+                Self::answered_by(group, alphabet, |answered| answered >= people)
+            }
+            Query::Exactly(count) => {
+                let people = count.people(group.len());
+                Self::answered_by(group, alphabet, |answered| answered == people)
+            }
+            Query::Not(query) => AnswerSet::full(alphabet).difference(&query.eval(group, alphabet)),
+            Query::And(queries) => queries
+                .iter()
+                .fold(AnswerSet::full(alphabet), |memo, query| {
+                    memo.intersection(&query.eval(group, alphabet))
+                }),
+            Query::Or(queries) => queries.iter().fold(AnswerSet::empty(), |memo, query| {
+                memo.union(&query.eval(group, alphabet))
+            }),
+        }
+    }
+
+    fn answered_by(
+        group: &[AnswerSet],
+        alphabet: usize,
+        keep: impl Fn(usize) -> bool,
+    ) -> AnswerSet {
+        let mut answers = AnswerSet::empty();
+        for index in 0..alphabet {
+            if keep(group.iter().filter(|person| person.contains(index)).count()) {
+                answers.insert(index);
+            }
+        }
+        answers
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        let rest = &self.input[self.position..];
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.input[self.position..].chars().next()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), Error> {
+        match self.peek() {
+            Some(c) if c == expected => {
+                self.position += c.len_utf8();
+                Ok(())
+            }
+            Some(c) => Err(Error::UnexpectedChar(self.position, c)),
+            None => Err(Error::UnexpectedEnd),
+        }
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        self.skip_whitespace();
+        let rest = &self.input[self.position..];
+        let len = rest.find(|c| !f(c)).unwrap_or(rest.len());
+        self.position += len;
+        &rest[..len]
+    }
+
+    fn count(&mut self) -> Result<Count, Error> {
+        let digits = self.take_while(|c| c.is_ascii_digit() || c == '%');
+        match digits.strip_suffix('%') {
+            Some(percent) => percent.parse().map(Count::Percent),
+            None => digits.parse().map(Count::People),
+        }
+        .map_err(|_| Error::BadCount(digits.to_string()))
+    }
+
+    fn queries(&mut self) -> Result<Vec<Query>, Error> {
+        let mut queries = vec![self.query()?];
+        while self.peek() == Some(',') {
+            self.expect(',')?;
+            queries.push(self.query()?);
+        }
+        Ok(queries)
+    }
+
+    fn query(&mut self) -> Result<Query, Error> {
+        let name = self.take_while(|c| c.is_ascii_alphabetic() || c == '_');
+        if name.is_empty() {
+            return match self.peek() {
+                Some(c) => Err(Error::UnexpectedChar(self.position, c)),
+                None => Err(Error::UnexpectedEnd),
+            };
+        }
+
+        let query = match name {
+            "union" => return Ok(Query::Union),
+            "intersect" => return Ok(Query::Intersect),
+            "at_least" | "exactly" => {
+                self.expect('(')?;
+                let count = self.count()?;
+                if name == "at_least" {
+                    Query::AtLeast(count)
+                } else {
+                    Query::Exactly(count)
+                }
+            }
+            "not" | "and" | "or" => {
+                self.expect('(')?;
+                let mut queries = self.queries()?;
+                match name {
+                    "not" if queries.len() == 1 => Query::Not(Box::new(queries.remove(0))),
+                    "not" => return Err(Error::WrongArgumentCount(name.to_string())),
+                    "and" => Query::And(queries),
+                    _ => Query::Or(queries),
+                }
+            }
+            _ => return Err(Error::UnknownFunction(name.to_string())),
+        };
+        self.expect(')')?;
+        Ok(query)
+    }
+}
+
+impl FromStr for Query {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            input: s,
+            position: 0,
+        };
+        let query = parser.query()?;
+        match parser.peek() {
+            Some(c) => Err(Error::UnexpectedChar(parser.position, c)),
+            None => Ok(query),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // "ab", "ac", "abd"
+    const GROUP: [AnswerSet; 3] = [
+        AnswerSet::Small(0b0011),
+        AnswerSet::Small(0b0101),
+        AnswerSet::Small(0b1011),
+    ];
+
+    fn eval(query: &str) -> u32 {
+        match query.parse::<Query>().unwrap().eval(&GROUP, 26) {
+            AnswerSet::Small(mask) => mask,
+            large => panic!("expected a small set, got {:?}", large),
+        }
+    }
+
+    #[test]
+    fn it_evaluates_queries() {
+        assert_eq!(eval("union"), 0b1111);
+        assert_eq!(eval("intersect"), 0b0001);
+        assert_eq!(eval("at_least(2)"), 0b0011);
+        assert_eq!(eval("at_least(50%)"), 0b0011);
+        assert_eq!(eval("exactly(1)"), 0b1100);
+        assert_eq!(eval("not(union)").count_ones(), 22);
+        assert_eq!(eval("and(at_least(2), not(intersect))"), 0b0010);
+        assert_eq!(eval(" or( exactly(1) , intersect ) "), 0b1101);
+    }
+
+    #[test]
+    fn it_rejects_bad_queries() {
+        assert_eq!("".parse::<Query>(), Err(Error::UnexpectedEnd));
+        assert_eq!(
+            "at_most(2)".parse::<Query>(),
+            Err(Error::UnknownFunction("at_most".to_string()))
+        );
+        assert_eq!(
+            "at_least(x)".parse::<Query>(),
+            Err(Error::BadCount("".to_string()))
+        );
+        assert_eq!(
+            "not(union, intersect)".parse::<Query>(),
+            Err(Error::WrongArgumentCount("not".to_string()))
+        );
+        assert_eq!(
+            "union)".parse::<Query>(),
+            Err(Error::UnexpectedChar(5, ')'))
+        );
+    }
+}
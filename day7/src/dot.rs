@@ -0,0 +1,111 @@
+use crate::graph::{BagGraph, Error};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    All,
+    Ancestors,
+    Descendants,
+}
+
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// Renders the containment graph as Graphviz DOT, one edge per rule labelled
+// with how many bags it holds. With a `focus` color, that color is highlighted
+// and `scope` can trim the graph down to the bags around it.
+pub fn export(graph: &BagGraph, focus: Option<&str>, scope: Scope) -> Result<String, Error> {
+    let focus = focus.map(|color| graph.id(color)).transpose()?;
+    let mut included = vec![scope == Scope::All; graph.len()];
+    if let Some(id) = focus {
+        let name = graph.name(id);
+        let related = match scope {
+            Scope::All => vec![],
+            Scope::Ancestors => graph.ancestors(name)?,
+            Scope::Descendants => graph.descendants(name)?,
+        };
+        included[id] = true;
+        for related in related {
+            included[related] = true;
+        }
+    }
+
+    let ids = (0..graph.len()).filter(|&id| included[id]);
+    let mut lines = vec!["digraph bags {".to_string()];
+    for id in ids.clone() {
+        let attrs = if Some(id) == focus {
+            " [style=filled, fillcolor=gold]"
+        } else {
+            ""
+        };
+        lines.push(format!("    {}{};", quote(graph.name(id)), attrs));
+    }
+    for from in ids {
+        for edge in graph.contents(from) {
+            if included[edge.to] {
+                lines.push(format!(
+                    "    {} -> {} [label=\"{}\"];",
+                    quote(graph.name(from)),
+                    quote(graph.name(edge.to)),
+                    edge.count
+                ));
+            }
+        }
+    }
+    lines.push("}".to_string());
+
+    Ok(lines.join("\n"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"light red bags contain 1 bright white bag, 2 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.
+shiny gold bags contain 1 dark olive bag.
+dark olive bags contain 3 faded blue bags.
+faded blue bags contain no other bags."#;
+
+    #[test]
+    fn it_exports_the_whole_graph() {
+        let graph: BagGraph = INPUT.parse().unwrap();
+        let dot = export(&graph, None, Scope::All).unwrap();
+
+        assert!(dot.starts_with("digraph bags {\n    \"light red\";"));
+        assert!(dot.contains("    \"muted yellow\" -> \"faded blue\" [label=\"9\"];"));
+        assert_eq!(dot.matches(" -> ").count(), 7);
+        assert!(!dot.contains("fillcolor"));
+    }
+
+    #[test]
+    fn it_restricts_to_ancestors_and_highlights() {
+        let graph: BagGraph = INPUT.parse().unwrap();
+        let dot = export(&graph, Some("shiny gold"), Scope::Ancestors).unwrap();
+
+        assert_eq!(
+            dot,
+            r#"digraph bags {
+    "light red";
+    "bright white";
+    "muted yellow";
+    "shiny gold" [style=filled, fillcolor=gold];
+    "light red" -> "bright white" [label="1"];
+    "light red" -> "muted yellow" [label="2"];
+    "bright white" -> "shiny gold" [label="1"];
+    "muted yellow" -> "shiny gold" [label="2"];
+}"#
+        );
+    }
+
+    #[test]
+    fn it_restricts_to_descendants() {
+        let graph: BagGraph = INPUT.parse().unwrap();
+        let dot = export(&graph, Some("shiny gold"), Scope::Descendants).unwrap();
+
+        assert_eq!(dot.matches(" -> ").count(), 2);
+        assert!(!dot.contains("light red"));
+        assert!(dot.contains("\"dark olive\" -> \"faded blue\" [label=\"3\"];"));
+    }
+}
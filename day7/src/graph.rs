@@ -0,0 +1,381 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+pub type ColorId = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub to: ColorId,
+    pub count: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    MissingContain(String),
+    MissingBag(String),
+    BadCount(String),
+    DuplicateRule(String),
+    UnknownColor(String),
+    Cycle(Vec<String>),
+    Overflow(String),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for Error {}
+
+// Bag colors interned to ids, with edges in both directions so queries can
+// walk down to what a bag holds or up to what holds it. Graphs are checked
+// for cycles on load, and `order` lists every color after all of its contents.
+#[derive(Debug, Default)]
+pub struct BagGraph {
+    names: Vec<String>,
+    ids: HashMap<String, ColorId>,
+    contains: Vec<Vec<Edge>>,
+    contained_in: Vec<Vec<Edge>>,
+    order: Vec<ColorId>,
+}
+
+// "shiny gold bags" or "shiny gold bag" => "shiny gold"
+fn color(bag: &str) -> Result<&str, Error> {
+    let bag = bag.trim();
+    bag.strip_suffix(" bags")
+        .or_else(|| bag.strip_suffix(" bag"))
+        .map(str::trim)
+        .filter(|color| !color.is_empty())
+        .ok_or_else(|| Error::MissingBag(bag.to_string()))
+}
+
+impl BagGraph {
+    fn intern(&mut self, color: &str) -> ColorId {
+        if let Some(&id) = self.ids.get(color) {
+            return id;
+        }
+        let id = self.names.len();
+        self.names.push(color.to_string());
+        self.ids.insert(color.to_string(), id);
+        self.contains.push(vec![]);
+        self.contained_in.push(vec![]);
+        id
+    }
+
+    // "light red bags contain 1 bright white bag, 2 muted yellow bags."
+    fn add_rule(&mut self, line: &str, seen: &mut HashSet<ColorId>) -> Result<(), Error> {
+        let line = line.trim().trim_end_matches('.');
+        let mut parts = line.splitn(2, " contain ");
+        let container = color(parts.next().unwrap_or_default())?;
+        let inner = parts
+            .next()
+            .ok_or_else(|| Error::MissingContain(line.to_string()))?;
+
+        let from = self.intern(container);
+        if !seen.insert(from) {
+            return Err(Error::DuplicateRule(container.to_string()));
+        }
+        if inner.trim() == "no other bags" {
+            return Ok(());
+        }
+
+        for bag in inner.split(", ") {
+            let mut rule = bag.trim().splitn(2, ' ');
+            let count = rule
+                .next()
+                .and_then(|count| count.parse().ok())
+                .ok_or_else(|| Error::BadCount(bag.to_string()))?;
+            let to = self.intern(color(rule.next().unwrap_or_default())?);
+            self.contains[from].push(Edge { to, count });
+            self.contained_in[to].push(Edge { to: from, count });
+        }
+
+        Ok(())
+    }
+
+    pub fn id(&self, color: &str) -> Result<ColorId, Error> {
+        self.ids
+            .get(color)
+            .copied()
+            .ok_or_else(|| Error::UnknownColor(color.to_string()))
+    }
+
+    pub fn name(&self, id: ColorId) -> &str {
+        &self.names[id]
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn contents(&self, id: ColorId) -> &[Edge] {
+        &self.contains[id]
+    }
+
+    fn reachable(edges: &[Vec<Edge>], start: ColorId) -> Vec<ColorId> {
+        let mut seen = HashSet::new();
+        let mut to_visit = vec![start];
+        let mut reached = vec![];
+        while let Some(id) = to_visit.pop() {
+            for edge in &edges[id] {
+                if seen.insert(edge.to) {
+                    reached.push(edge.to);
+                    to_visit.push(edge.to);
+                }
+            }
+        }
+        reached.sort_unstable();
+        reached
+    }
+
+    // Every color that can eventually hold a `color` bag.
+    pub fn ancestors(&self, color: &str) -> Result<Vec<ColorId>, Error> {
+        Ok(Self::reachable(&self.contained_in, self.id(color)?))
+    }
+
+    // Every color a `color` bag eventually holds.
+    pub fn descendants(&self, color: &str) -> Result<Vec<ColorId>, Error> {
+        Ok(Self::reachable(&self.contains, self.id(color)?))
+    }
+
+    // Depth first over `contains`, without recursion so deep rule sets can't
+    // overflow the stack. Fills `order` or returns the first cycle found.
+    fn sort(&mut self) -> Result<(), Error> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            Unvisited,
+            OnStack,
+            Done,
+        }
+
+        let mut state = vec![State::Unvisited; self.names.len()];
+        let mut order = Vec::with_capacity(self.names.len());
+
+        for root in 0..self.names.len() {
+            if state[root] != State::Unvisited {
+                continue;
+            }
+            state[root] = State::OnStack;
+            let mut stack = vec![(root, 0)];
+
+            while let Some((id, next_edge)) = stack.last_mut() {
+                let id = *id;
+                match self.contains[id].get(*next_edge) {
+                    Some(edge) => {
+                        *next_edge += 1;
+                        match state[edge.to] {
+                            State::Unvisited => {
+                                state[edge.to] = State::OnStack;
+                                stack.push((edge.to, 0));
+                            }
+                            State::OnStack => {
+                                let start = stack.iter().position(|&(id, _)| id == edge.to);
+                                let cycle = stack[start.unwrap_or_default()..]
+                                    .iter()
+                                    .map(|&(id, _)| id)
+                                    .chain(std::iter::once(edge.to))
+                                    .map(|id| self.names[id].clone())
+                                    .collect();
+                                return Err(Error::Cycle(cycle));
+                            }
+                            State::Done => {}
+                        }
+                    }
+                    None => {
+                        state[id] = State::Done;
+                        order.push(id);
+                        stack.pop();
+                    }
+                }
+            }
+        }
+
+        self.order = order;
+        Ok(())
+    }
+
+    // How many bags are inside a single `color` bag. Every color's total is
+    // worked out once, contents first; a total that doesn't fit is only an
+    // error when it's the one asked for.
+    pub fn total_contained(&self, color: &str) -> Result<u128, Error> {
+        let id = self.id(color)?;
+        let mut totals: Vec<Option<u128>> = vec![None; self.names.len()];
+
+        for &bag in &self.order {
+            totals[bag] = self.contains[bag].iter().try_fold(0_u128, |memo, edge| {
+                let count = edge.count as u128;
+                totals[edge.to]?
+                    .checked_add(1)?
+                    .checked_mul(count)?
+                    .checked_add(memo)
+            });
+        }
+
+        totals[id].ok_or_else(|| Error::Overflow(color.to_string()))
+    }
+
+    // The shortest chain of bags from `outer` down to `inner`, both included.
+    pub fn path(&self, outer: &str, inner: &str) -> Result<Option<Vec<ColorId>>, Error> {
+        let (start, end) = (self.id(outer)?, self.id(inner)?);
+        let mut previous = HashMap::new();
+        let mut to_visit = VecDeque::from(vec![start]);
+
+        while let Some(id) = to_visit.pop_front() {
+            if id == end {
+                let mut path = vec![end];
+                while let Some(&id) = previous.get(path.last().unwrap()) {
+                    path.push(id);
+                }
+                path.reverse();
+                return Ok(Some(path));
+            }
+            for edge in &self.contains[id] {
+                if edge.to != start && !previous.contains_key(&edge.to) {
+                    previous.insert(edge.to, id);
+                    to_visit.push_back(edge.to);
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl std::str::FromStr for BagGraph {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut graph = Self::default();
+        let mut seen = HashSet::new();
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            graph.add_rule(line, &mut seen)?;
+        }
+        graph.sort()?;
+        Ok(graph)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const INPUT: &str = r#"light red bags contain 1 bright white bag, 2 muted yellow bags.
+dark orange bags contain 3 bright white bags, 4 muted yellow bags.
+bright white bags contain 1 shiny gold bag.
+muted yellow bags contain 2 shiny gold bags, 9 faded blue bags.
+shiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.
+dark olive bags contain 3 faded blue bags, 4 dotted black bags.
+vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.
+faded blue bags contain no other bags.
+dotted black bags contain no other bags."#;
+
+    fn names(graph: &BagGraph, ids: Vec<ColorId>) -> Vec<&str> {
+        let mut names = ids.into_iter().map(|id| graph.name(id)).collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn it_matches_exact_colors() {
+        let graph: BagGraph = "gold bags contain 1 plum bag.\nshiny gold bags contain 2 dark gold bags.\nred bags contain 1 shiny gold bag."
+            .parse()
+            .unwrap();
+
+        assert_eq!(
+            names(&graph, graph.ancestors("gold").unwrap()),
+            Vec::<&str>::new()
+        );
+        assert_eq!(
+            names(&graph, graph.ancestors("shiny gold").unwrap()),
+            vec!["red"]
+        );
+        assert_eq!(graph.total_contained("red").unwrap(), 3);
+    }
+
+    #[test]
+    fn it_queries_the_example() {
+        let graph: BagGraph = INPUT.parse().unwrap();
+
+        assert_eq!(
+            names(&graph, graph.ancestors("shiny gold").unwrap()),
+            vec!["bright white", "dark orange", "light red", "muted yellow"]
+        );
+        assert_eq!(
+            names(&graph, graph.descendants("shiny gold").unwrap()),
+            vec!["dark olive", "dotted black", "faded blue", "vibrant plum"]
+        );
+        assert_eq!(graph.total_contained("shiny gold").unwrap(), 32);
+
+        let path = graph.path("light red", "faded blue").unwrap().unwrap();
+        assert_eq!(
+            path.into_iter()
+                .map(|id| graph.name(id))
+                .collect::<Vec<_>>(),
+            vec!["light red", "muted yellow", "faded blue"]
+        );
+        assert_eq!(graph.path("faded blue", "light red").unwrap(), None);
+    }
+
+    #[test]
+    fn it_rejects_bad_rules() {
+        assert_eq!(
+            "red bags".parse::<BagGraph>().unwrap_err(),
+            Error::MissingContain("red bags".to_string())
+        );
+        assert_eq!(
+            "red bags contain two blue bags."
+                .parse::<BagGraph>()
+                .unwrap_err(),
+            Error::BadCount("two blue bags".to_string())
+        );
+        assert_eq!(
+            "red bags contain no other bags.\nred bags contain 1 blue bag."
+                .parse::<BagGraph>()
+                .unwrap_err(),
+            Error::DuplicateRule("red".to_string())
+        );
+        let graph: BagGraph = INPUT.parse().unwrap();
+        assert_eq!(
+            graph.ancestors("gold").unwrap_err(),
+            Error::UnknownColor("gold".to_string())
+        );
+    }
+
+    #[test]
+    fn it_reports_cycles() {
+        let rules = "red bags contain 1 blue bag.\nblue bags contain 2 green bags, 1 white bag.\ngreen bags contain 1 red bag.\nwhite bags contain no other bags.";
+        assert_eq!(
+            rules.parse::<BagGraph>().unwrap_err(),
+            Error::Cycle(vec![
+                "red".to_string(),
+                "blue".to_string(),
+                "green".to_string(),
+                "red".to_string()
+            ])
+        );
+        assert_eq!(
+            "red bags contain 1 red bag."
+                .parse::<BagGraph>()
+                .unwrap_err(),
+            Error::Cycle(vec!["red".to_string(), "red".to_string()])
+        );
+    }
+
+    #[test]
+    fn it_counts_deep_shared_chains() {
+        // Each level holds two of the next, twice over, so a naive walk would
+        // visit 4^200 bags and the total doesn't fit in a u128.
+        let rules = (0..200)
+            .map(|i| format!("l{} bags contain 1 a{} bag, 1 b{} bag.\na{} bags contain 2 l{} bags.\nb{} bags contain 2 l{} bags.", i, i, i, i, i + 1, i, i + 1))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let graph: BagGraph = rules.parse().unwrap();
+
+        assert_eq!(graph.total_contained("l199").unwrap(), 2 + 4);
+        assert_eq!(graph.total_contained("l198").unwrap(), 2 + 4 * (1 + 6));
+        assert_eq!(
+            graph.total_contained("l0").unwrap_err(),
+            Error::Overflow("l0".to_string())
+        );
+    }
+}
@@ -1,77 +1,85 @@
-type RulesMap = std::collections::HashMap<String, Vec<(String, usize)>>;
+mod dot;
+mod graph;
 
-fn part1(rules: &RulesMap) -> usize {
-    fn find_matching_inner<'a>(rules: &'a RulesMap, mut key: &'_ str) -> Vec<&'a str> {
-        if key.ends_with('s') {
-            key = &key[..key.len() - 1] // drop s;
-        }
-        rules.iter().filter_map(|(outer, inner)| {
-            if inner.iter().any(|(color, _)| color.contains(key)) {
-                Some(&outer[..])
-            } else {
-                None
-            }
-        }).collect()
-    }
-    let mut color_to_be_searched = vec!["shiny gold bag"];
-    let mut results = vec![];
-    let mut searched = std::collections::HashSet::new();
-    while let Some(color) = color_to_be_searched.pop() {
-        match searched.insert(color) {
-            false => continue,
-            true => {
-                results.push(color);
-                color_to_be_searched.append(&mut find_matching_inner(&rules, color));
-            }
-        };
-    }
+use dot::Scope;
+use graph::BagGraph;
 
-    results.len() - 1
+fn part1(graph: &BagGraph) -> Result<usize, graph::Error> {
+    Ok(graph.ancestors("shiny gold")?.len())
 }
 
-use std::borrow::Cow;
-
-fn part2(rules: &RulesMap) -> usize {
-    fn count_matching_inner<'a>(rules: &'a RulesMap, mut key: &'_ str) -> usize {
-        let mut plural_key = Cow::from(key);
-
-        if !key.ends_with('s') {
-            plural_key += "s";
-        };
-        let rule = rules.get(plural_key.as_ref()).expect(&format!("unknown key {}", key));
-        rule.iter().map(|(key, num)| {
-            count_matching_inner(&rules, key) * num + num
-        }).sum()
-    }
+fn part2(graph: &BagGraph) -> Result<u128, graph::Error> {
+    graph.total_contained("shiny gold")
+}
 
-    count_matching_inner(&rules, "shiny gold bags")
+fn names(graph: &BagGraph, ids: &[graph::ColorId]) -> String {
+    ids.iter()
+        .map(|&id| graph.name(id))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
-fn create_rules_map(input: &str) -> RulesMap {
-    input.lines().map(|line| {
-        let line = &line[..(line.len() - 1)]; // drop the full stop
-        let mut parts = line.splitn(2, "contain ");
-        let container = parts.next().expect("coudln't find a container").trim();
-        let inner_parts = parts.next().expect("coudln't find a inner").split(", ").filter_map(|inner| {
-            if inner.contains("no other bags") {
-                None
-            } else {
-                let mut rule = inner.splitn(2, " ");
-                let number = rule.next().expect("couldn't find number for rule").parse().expect("couldn't parse rule number");
-                let color = rule.next().expect("couldn't find color for rule").trim();
-                Some((color.to_string(), number))
+// --ancestors COLOR, --descendants COLOR, --count COLOR and --path OUTER INNER
+// run a single query against the rules. --dot prints the whole graph as DOT;
+// --dot-highlight, --dot-ancestors and --dot-descendants take a COLOR to focus on.
+fn run_args(
+    graph: &BagGraph,
+    mut args: impl Iterator<Item = String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    while let Some(flag) = args.next() {
+        let mut color = || {
+            args.next()
+                .ok_or_else(|| format!("missing color for {}", flag))
+        };
+        match flag.as_str() {
+            "--dot" => println!("{}", dot::export(graph, None, Scope::All)?),
+            "--dot-highlight" => println!("{}", dot::export(graph, Some(&color()?), Scope::All)?),
+            "--dot-ancestors" => {
+                println!("{}", dot::export(graph, Some(&color()?), Scope::Ancestors)?)
+            }
+            "--dot-descendants" => println!(
+                "{}",
+                dot::export(graph, Some(&color()?), Scope::Descendants)?
+            ),
+            "--ancestors" => {
+                let color = color()?;
+                println!(
+                    "{} is inside: {}",
+                    color,
+                    names(graph, &graph.ancestors(&color)?)
+                );
+            }
+            "--descendants" => {
+                let color = color()?;
+                println!(
+                    "{} holds: {}",
+                    color,
+                    names(graph, &graph.descendants(&color)?)
+                );
+            }
+            "--count" => {
+                let color = color()?;
+                println!("{} holds {} bags", color, graph.total_contained(&color)?);
             }
-        }).collect::<Vec<_>>();
-        (container.to_string(), inner_parts)
-    }).collect::<RulesMap>()
+            "--path" => {
+                let (outer, inner) = (color()?, color()?);
+                match graph.path(&outer, &inner)? {
+                    Some(path) => println!("{}", names(graph, &path)),
+                    None => println!("{} can't hold {}", outer, inner),
+                }
+            }
+            _ => return Err(format!("unknown flag {}", flag).into()),
+        }
+    }
+    Ok(())
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>>{
+fn main() -> Result<(), Box<dyn std::error::Error>> {
     let input = std::fs::read_to_string("input.txt")?;
-    let rules = create_rules_map(&input);
-    println!("part1: {}", part1(&rules));
-    println!("part2: {}", part2(&rules));
-    Ok(())
+    let graph: BagGraph = input.parse()?;
+    println!("part1: {}", part1(&graph)?);
+    println!("part2: {}", part2(&graph)?);
+    run_args(&graph, std::env::args().skip(1))
 }
 
 #[cfg(test)]
@@ -88,16 +96,16 @@ dark olive bags contain 3 faded blue bags, 4 dotted black bags.
 vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.
 faded blue bags contain no other bags.
 dotted black bags contain no other bags."#;
-        let rules = create_rules_map(&input);
-        assert_eq!(part1(&rules), 4);
+        let graph: BagGraph = input.parse().unwrap();
+        assert_eq!(part1(&graph).unwrap(), 4);
     }
 
     #[test]
     fn it_works_bright_white() {
         let input = "bright white bags contain 1 shiny gold bag.";
-        let rules = create_rules_map(&input);
+        let graph: BagGraph = input.parse().unwrap();
 
-        assert_eq!(part1(&rules), 1);
+        assert_eq!(part1(&graph).unwrap(), 1);
     }
 
     #[test]
@@ -111,7 +119,7 @@ dark olive bags contain 3 faded blue bags, 4 dotted black bags.
 vibrant plum bags contain 5 faded blue bags, 6 dotted black bags.
 faded blue bags contain no other bags.
 dotted black bags contain no other bags."#;
-        let rules = create_rules_map(&input);
-        assert_eq!(part2(&rules), 32);
+        let graph: BagGraph = input.parse().unwrap();
+        assert_eq!(part2(&graph).unwrap(), 32);
     }
 }
@@ -0,0 +1,236 @@
+use crate::computer::Op;
+use crate::dialect::Control;
+use std::collections::VecDeque;
+
+// The control-flow graph of a program. Nodes are instruction indices plus one
+// extra node, `exit`, for running off the end (index == len) or `hlt`. Jumps
+// anywhere else out of the program crash, so they get no edge at all.
+//
+// Conditional jumps get both edges, so with the extended dialect the graph
+// over-approximates what can actually run.
+pub struct Cfg {
+    successors: Vec<Vec<usize>>,
+    predecessors: Vec<Vec<usize>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Flip {
+    pub index: usize,
+    pub from: Op,
+    pub to: Op,
+}
+
+fn target(len: usize, index: usize, offset: Option<i64>) -> Option<usize> {
+    let target = (index as i64).checked_add(offset?)?;
+    if (0..=len as i64).contains(&target) {
+        Some(target as usize)
+    } else {
+        None
+    }
+}
+
+fn successors(ops: &[Op], index: usize, op: &Op) -> Vec<usize> {
+    let len = ops.len();
+    let next = index + 1;
+    match op.opcode.control {
+        Control::Halt => vec![len],
+        Control::Jump => target(len, index, op.offset()).into_iter().collect(),
+        Control::Branch => {
+            let mut targets = vec![next];
+            targets.extend(target(len, index, op.offset()).filter(|&t| t != next));
+            targets
+        }
+        Control::Next => vec![next],
+    }
+}
+
+fn search(edges: &[Vec<usize>], start: usize) -> Vec<bool> {
+    let mut seen = vec![false; edges.len()];
+    let mut queue = VecDeque::new();
+    seen[start] = true;
+    queue.push_back(start);
+    while let Some(node) = queue.pop_front() {
+        for &next in &edges[node] {
+            if !seen[next] {
+                seen[next] = true;
+                queue.push_back(next);
+            }
+        }
+    }
+    seen
+}
+
+impl Cfg {
+    pub fn new(ops: &[Op]) -> Self {
+        let mut successors: Vec<Vec<usize>> = ops
+            .iter()
+            .enumerate()
+            .map(|(index, op)| self::successors(ops, index, op))
+            .collect();
+        successors.push(vec![]);
+
+        let mut predecessors = vec![vec![]; successors.len()];
+        for (from, targets) in successors.iter().enumerate() {
+            for &to in targets {
+                predecessors[to].push(from);
+            }
+        }
+        Self {
+            successors,
+            predecessors,
+        }
+    }
+
+    pub fn exit(&self) -> usize {
+        self.successors.len() - 1
+    }
+
+    // Indexed by instruction, with `exit` last.
+    pub fn reachable(&self) -> Vec<bool> {
+        search(&self.successors, 0)
+    }
+
+    // The instructions that can reach `exit`, found by walking the reversed
+    // graph back from it.
+    pub fn terminating(&self) -> Vec<bool> {
+        search(&self.predecessors, self.exit())
+    }
+
+    // Whether `exit` can be reached from the start once the instruction at
+    // `index` goes to `replacement` instead.
+    fn terminates_with(&self, index: usize, replacement: &[usize]) -> bool {
+        let mut seen = vec![false; self.successors.len()];
+        let mut stack = vec![0];
+        seen[0] = true;
+        while let Some(node) = stack.pop() {
+            if node == self.exit() {
+                return true;
+            }
+            let edges = if node == index {
+                replacement
+            } else {
+                &self.successors[node]
+            };
+            for &next in edges {
+                if !seen[next] {
+                    seen[next] = true;
+                    stack.push(next);
+                }
+            }
+        }
+        false
+    }
+}
+
+// Every single jmp/nop swap that lets the program reach the end. A flip only
+// matters on an instruction that runs. If that instruction couldn't reach the
+// exit before, no path from it to the exit goes back through it, so the flip
+// works if the new successor could already reach the exit. Otherwise the
+// existing path might lean on the very instruction being flipped, so the
+// flipped graph is searched again.
+pub fn flips(ops: &[Op]) -> Vec<Flip> {
+    let cfg = Cfg::new(ops);
+    let reachable = cfg.reachable();
+    let terminating = cfg.terminating();
+
+    ops.iter()
+        .enumerate()
+        .filter(|&(index, _)| reachable[index])
+        .filter_map(|(index, from)| {
+            let to = from.flipped()?;
+            let next = successors(ops, index, &to);
+            let works = if terminating[index] {
+                cfg.terminates_with(index, &next)
+            } else {
+                next.iter().all(|&next| terminating[next])
+            };
+            Some(Flip {
+                index,
+                from: from.clone(),
+                to,
+            })
+            .filter(|_| works)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::computer::{Computer, Termination};
+
+    const PROGRAM: &str = "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6";
+
+    #[test]
+    fn it_finds_reachable_and_terminating_instructions() {
+        let computer: Computer = PROGRAM.parse().unwrap();
+        let cfg = Cfg::new(&computer.ops);
+        let reachable = cfg.reachable();
+        let terminating = cfg.terminating();
+
+        assert_eq!(
+            (0..=9).filter(|&i| reachable[i]).collect::<Vec<_>>(),
+            vec![0, 1, 2, 3, 4, 6, 7]
+        );
+        assert_eq!(
+            (0..=9).filter(|&i| terminating[i]).collect::<Vec<_>>(),
+            vec![8, 9]
+        );
+    }
+
+    #[test]
+    fn it_finds_the_same_flip_as_brute_force() {
+        let mut computer: Computer = PROGRAM.parse().unwrap();
+        let found = flips(&computer.ops);
+        assert_eq!(
+            found,
+            vec![Flip {
+                index: 7,
+                from: "jmp -4".parse().unwrap(),
+                to: "nop -4".parse().unwrap()
+            }]
+        );
+
+        let brute_force: Vec<usize> = (0..computer.ops.len())
+            .filter(|&i| {
+                let original = computer.ops[i].clone();
+                let flipped = match original.flipped() {
+                    Some(flipped) => flipped,
+                    None => return false,
+                };
+                computer.ops[i] = flipped;
+                let terminates = computer.run().unwrap() == Termination::Halted;
+                computer.ops[i] = original;
+                terminates
+            })
+            .collect();
+        assert_eq!(brute_force, vec![7]);
+    }
+
+    #[test]
+    fn it_reports_every_candidate() {
+        // Jumping over the end or falling through to it both work.
+        let computer: Computer = "nop +2\njmp -1".parse().unwrap();
+        let found = flips(&computer.ops);
+        assert_eq!(
+            found.iter().map(|flip| flip.index).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+    }
+
+    #[test]
+    fn it_rechecks_flips_of_a_program_that_already_halts() {
+        // Runs 0, 2, 1 and halts. Flipping 1 would loop between 2 and 1.
+        let mut computer: Computer = "jmp +2\njmp +2\njmp -1".parse().unwrap();
+        let found: Vec<usize> = flips(&computer.ops).iter().map(|flip| flip.index).collect();
+        assert_eq!(found, vec![0, 2]);
+
+        for i in 0..computer.ops.len() {
+            let original = computer.ops[i].clone();
+            computer.ops[i] = original.flipped().unwrap();
+            let halts = computer.run().unwrap() == Termination::Halted;
+            computer.ops[i] = original;
+            assert_eq!(halts, found.contains(&i), "flip {}", i);
+        }
+    }
+}
@@ -0,0 +1,475 @@
+use crate::dialect::{self, Control, Dialect, Flow, Opcode};
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub enum Error {
+    CantParse(String),
+    CouldntGetOps(usize, String),
+    OutOfBounds(i64),
+    UnknownOpcode(String),
+    NoInput(usize),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::CantParse(message) => write!(f, "{}", message),
+            Error::CouldntGetOps(index, message) => write!(f, "op {}: {}", index, message),
+            Error::OutOfBounds(addr) => write!(f, "jumped out of bounds to {}", addr),
+            Error::UnknownOpcode(line) => write!(f, "unknown opcode in {:?}", line),
+            Error::NoInput(register) => write!(f, "no input left for instruction {}", register),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Register(pub usize);
+
+impl Register {
+    pub const COUNT: usize = 8;
+    pub const ACC: Register = Register(0);
+}
+
+// `acc` is register 0, the rest are `r1` to `r7`.
+impl FromStr for Register {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "acc" {
+            return Ok(Register::ACC);
+        }
+        s.strip_prefix('r')
+            .and_then(|n| n.parse::<usize>().ok())
+            .filter(|&n| n > 0 && n < Register::COUNT)
+            .map(Register)
+            .ok_or_else(|| Error::CantParse(format!("unknown register {}", s)))
+    }
+}
+
+impl std::fmt::Display for Register {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            0 => write!(f, "acc"),
+            n => write!(f, "r{}", n),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Reg(Register),
+    Imm(i64),
+}
+
+impl FromStr for Operand {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<i64>() {
+            Ok(value) => Ok(Operand::Imm(value)),
+            Err(_) => s.parse().map(Operand::Reg),
+        }
+    }
+}
+
+impl std::fmt::Display for Operand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Operand::Reg(register) => write!(f, "{}", register),
+            Operand::Imm(value) => write!(f, "{:+}", value),
+        }
+    }
+}
+
+// One instruction: the opcode its mnemonic was registered with, and the
+// operands it was written with.
+#[derive(Debug, Clone)]
+pub struct Op {
+    pub mnemonic: &'static str,
+    pub operands: Vec<Operand>,
+    pub opcode: Opcode,
+}
+
+impl PartialEq for Op {
+    fn eq(&self, other: &Self) -> bool {
+        self.mnemonic == other.mnemonic && self.operands == other.operands
+    }
+}
+
+impl Eq for Op {}
+
+impl FromStr for Op {
+    type Err = Error;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        Dialect::default().parse(line)
+    }
+}
+
+impl std::fmt::Display for Op {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.mnemonic)?;
+        for operand in &self.operands {
+            write!(f, " {}", operand)?;
+        }
+        Ok(())
+    }
+}
+
+impl Op {
+    // Whether the next instruction can depend on register values.
+    pub fn is_conditional(&self) -> bool {
+        self.opcode.control == Control::Branch
+    }
+
+    // How far a jump or branch goes when it's taken.
+    pub fn offset(&self) -> Option<i64> {
+        match (self.opcode.control, self.operands.last()) {
+            (Control::Jump, Some(Operand::Imm(offset)))
+            | (Control::Branch, Some(Operand::Imm(offset))) => Some(*offset),
+            _ => None,
+        }
+    }
+
+    // The jmp/nop swap the puzzle's corruption is about.
+    pub fn flipped(&self) -> Option<Op> {
+        let (mnemonic, opcode) = match self.mnemonic {
+            "jmp" => ("nop", dialect::NOP),
+            "nop" => ("jmp", dialect::JMP),
+            _ => return None,
+        };
+        Some(Op {
+            mnemonic,
+            operands: self.operands.clone(),
+            opcode,
+        })
+    }
+
+    fn run(&self, ctx: &mut Ctx) -> Result<(), Error> {
+        match (self.opcode.execute)(ctx, &self.operands)? {
+            Flow::Next => ctx.register += 1,
+            Flow::Halt => ctx.halted = true,
+            Flow::Jump(offset) => {
+                let addr = (ctx.register as i64).saturating_add(offset);
+                ctx.register = usize::try_from(addr).map_err(|_| Error::OutOfBounds(addr))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Ctx {
+    pub registers: [i64; Register::COUNT],
+    pub register: usize,
+    pub halted: bool,
+    pub input: VecDeque<i64>,
+    pub output: Vec<i64>,
+}
+
+impl Ctx {
+    pub fn accumulator(&self) -> i64 {
+        self.registers[Register::ACC.0]
+    }
+
+    pub fn value(&self, operand: Operand) -> i64 {
+        match operand {
+            Operand::Reg(register) => self.registers[register.0],
+            Operand::Imm(value) => value,
+        }
+    }
+
+    pub fn register_mut(&mut self, operand: Operand) -> Result<&mut i64, Error> {
+        match operand {
+            Operand::Reg(register) => Ok(&mut self.registers[register.0]),
+            Operand::Imm(value) => Err(Error::CantParse(format!(
+                "expected a register, not {:+}",
+                value
+            ))),
+        }
+    }
+}
+
+// How a run ended. `Halted` is the only clean exit: running onto the
+// instruction just past the end, or `hlt`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Termination {
+    Halted,
+    OutOfBounds(i64),
+    // `trace` is the last `Computer::TRACE_LEN` instructions run, ending just
+    // before `first_repeat` would run again. Without conditional jumps that's
+    // the first instruction to repeat; with them the loop may have gone round
+    // a few more times before `LoopDetector` noticed.
+    InfiniteLoop {
+        first_repeat: usize,
+        trace: Vec<usize>,
+    },
+    StepLimit,
+}
+
+// Everything that decides where a program with conditional jumps goes next.
+type State = (usize, [i64; Register::COUNT], usize);
+
+// Spots a program that's stuck, without remembering every state. Without
+// conditional jumps the pointer alone decides where it goes next, so one
+// flag per instruction is enough. With them the registers and how much input
+// is left matter too, and Brent's algorithm finds a repeat while holding on
+// to a single state: the one at the last power-of-two step.
+pub struct LoopDetector {
+    visited: Option<Vec<bool>>,
+    saved: Option<State>,
+    power: usize,
+    since_saved: usize,
+}
+
+impl LoopDetector {
+    pub fn new(ops: &[Op]) -> Self {
+        let conditional = ops.iter().any(Op::is_conditional);
+        Self {
+            visited: Some(vec![false; ops.len()]).filter(|_| !conditional),
+            saved: None,
+            power: 1,
+            since_saved: 1,
+        }
+    }
+
+    // Notes the state before the next instruction runs, returning whether
+    // the program is now going round in a loop.
+    pub fn repeated(&mut self, ctx: &Ctx) -> bool {
+        if let Some(visited) = &mut self.visited {
+            return std::mem::replace(&mut visited[ctx.register], true);
+        }
+
+        let state = (ctx.register, ctx.registers, ctx.input.len());
+        if self.saved == Some(state) {
+            return true;
+        }
+        if self.since_saved == self.power {
+            self.saved = Some(state);
+            self.power *= 2;
+            self.since_saved = 0;
+        }
+        self.since_saved += 1;
+        false
+    }
+}
+
+pub struct Computer {
+    pub ops: Vec<Op>,
+    pub ctx: Ctx,
+    // Runs give up after this many instructions, since a program whose
+    // registers never repeat would otherwise run forever.
+    pub step_limit: usize,
+}
+
+impl FromStr for Computer {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self::new(Dialect::default().parse_program(s)?))
+    }
+}
+
+impl Computer {
+    pub const DEFAULT_STEP_LIMIT: usize = 1_000_000;
+    pub const TRACE_LEN: usize = 1000;
+
+    pub fn new(ops: Vec<Op>) -> Self {
+        Self {
+            ops,
+            ctx: Ctx::default(),
+            step_limit: Self::DEFAULT_STEP_LIMIT,
+        }
+    }
+
+    pub fn step(&mut self) -> Result<(usize, i64), Error> {
+        let op = self.ops.get(self.ctx.register).ok_or_else(|| {
+            Error::CouldntGetOps(
+                self.ctx.register,
+                format!(
+                    "tried to get {} but len is only {}",
+                    self.ctx.register,
+                    self.ops.len()
+                ),
+            )
+        })?;
+        op.run(&mut self.ctx)?;
+        Ok((self.ctx.register, self.ctx.accumulator()))
+    }
+
+    // Whether the pointer has left the program, and how.
+    pub fn exited(&self) -> Option<Termination> {
+        let len = self.ops.len();
+        if self.ctx.halted || self.ctx.register == len {
+            Some(Termination::Halted)
+        } else if self.ctx.register > len {
+            Some(Termination::OutOfBounds(self.ctx.register as i64))
+        } else {
+            None
+        }
+    }
+
+    // Puzzle programs loop forever as soon as an instruction repeats, but with
+    // conditional jumps it's only a loop if the registers repeat too. The step
+    // limit is what ends programs that never repeat.
+    pub fn run(&mut self) -> Result<Termination, Error> {
+        self.reset();
+        let mut detector = LoopDetector::new(&self.ops);
+        let mut trace = VecDeque::with_capacity(Self::TRACE_LEN);
+        let mut steps = 0;
+
+        loop {
+            if let Some(termination) = self.exited() {
+                return Ok(termination);
+            }
+            let register = self.ctx.register;
+            if detector.repeated(&self.ctx) {
+                return Ok(Termination::InfiniteLoop {
+                    first_repeat: register,
+                    trace: trace.into(),
+                });
+            }
+            if steps >= self.step_limit {
+                return Ok(Termination::StepLimit);
+            }
+            steps += 1;
+            if trace.len() == Self::TRACE_LEN {
+                trace.pop_front();
+            }
+            trace.push_back(register);
+
+            match self.step() {
+                Ok(_) => (),
+                Err(Error::OutOfBounds(addr)) => return Ok(Termination::OutOfBounds(addr)),
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    // Clears registers, pointer and output, keeping any queued input.
+    pub fn reset(&mut self) {
+        let input = std::mem::take(&mut self.ctx.input);
+        self.ctx = Ctx {
+            input,
+            ..Ctx::default()
+        };
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn it_runs_the_puzzle_dialect() {
+        let input = "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\nnop -4\nacc +6";
+        let mut computer: Computer = input.parse().unwrap();
+        assert_eq!(computer.run().unwrap(), Termination::Halted);
+        assert_eq!(computer.ctx.accumulator(), 8);
+        assert!("add acc 1".parse::<Computer>().is_err());
+    }
+
+    #[test]
+    fn it_runs_extended_programs() {
+        // Multiplies the two inputs by repeated addition.
+        let program = r#"
+in r1
+in r2
+set acc 0
+jz r2 +4
+add acc r1
+sub r2 1
+jmp -3
+out acc
+hlt
+out 99
+"#;
+        let ops = Dialect::extended().parse_program(program).unwrap();
+        let mut computer = Computer::new(ops);
+        computer.ctx.input.extend(vec![6, 7]);
+
+        assert_eq!(computer.run().unwrap(), Termination::Halted);
+        assert_eq!(computer.ctx.accumulator(), 42);
+        assert_eq!(computer.ctx.output, vec![42]);
+        assert_eq!(computer.ctx.registers[2], 0);
+
+        let ops = Dialect::extended()
+            .parse_program("set acc 5000000000\nout acc")
+            .unwrap();
+        let mut computer = Computer::new(ops);
+        assert_eq!(computer.run().unwrap(), Termination::Halted);
+        assert_eq!(computer.ctx.accumulator(), 5_000_000_000);
+        assert_eq!(computer.ctx.output, vec![5_000_000_000]);
+    }
+
+    #[test]
+    fn it_errors_without_input() {
+        let ops = Dialect::extended().parse_program("in acc").unwrap();
+        assert!(matches!(Computer::new(ops).run(), Err(Error::NoInput(0))));
+    }
+
+    #[test]
+    fn it_reports_how_a_run_ended() {
+        let mut computer: Computer = "nop +0\njmp -2".parse().unwrap();
+        assert_eq!(computer.run().unwrap(), Termination::OutOfBounds(-1));
+        assert_eq!(computer.ctx.register, 1);
+
+        let mut computer: Computer = "acc +1\njmp +5".parse().unwrap();
+        assert_eq!(computer.run().unwrap(), Termination::OutOfBounds(6));
+
+        let mut computer: Computer = "acc +1\nnop +0\njmp -1".parse().unwrap();
+        assert_eq!(
+            computer.run().unwrap(),
+            Termination::InfiniteLoop {
+                first_repeat: 1,
+                trace: vec![0, 1, 2]
+            }
+        );
+
+        let ops = Dialect::extended()
+            .parse_program("add r1 1\njnz r1 -1")
+            .unwrap();
+        let mut computer = Computer::new(ops);
+        computer.step_limit = 1000;
+        assert_eq!(computer.run().unwrap(), Termination::StepLimit);
+
+        assert_eq!(computer.ctx.registers[1], 500);
+
+        // Counts r1 down from 3 to 0 and back to 3 forever.
+        let ops = Dialect::extended()
+            .parse_program("set r1 3\nsub r1 1\njnz r1 -1\nset r1 3\njmp -3")
+            .unwrap();
+        let mut countdown = Computer::new(ops);
+        assert!(matches!(
+            countdown.run().unwrap(),
+            Termination::InfiniteLoop { .. }
+        ));
+
+        // Without --steps the default still stops it, and long loops only
+        // keep the end of the trace.
+        let mut computer = Computer::new(computer.ops);
+        assert_eq!(computer.run().unwrap(), Termination::StepLimit);
+        assert_eq!(
+            computer.ctx.registers[1] as usize,
+            Computer::DEFAULT_STEP_LIMIT / 2
+        );
+
+        let program = "nop +0\n".repeat(Computer::TRACE_LEN * 2) + "jmp -2000";
+        let mut computer: Computer = program.parse().unwrap();
+        match computer.run().unwrap() {
+            Termination::InfiniteLoop {
+                first_repeat,
+                trace,
+            } => {
+                assert_eq!(first_repeat, 0);
+                assert_eq!(trace.len(), Computer::TRACE_LEN);
+                assert_eq!(trace[trace.len() - 1], Computer::TRACE_LEN * 2);
+            }
+            termination => panic!("expected a loop, got {:?}", termination),
+        }
+    }
+}
@@ -0,0 +1,517 @@
+use crate::computer::{Computer, Error, LoopDetector, Op, Termination};
+use std::collections::VecDeque;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Breakpoint {
+    Index(usize),
+    Opcode(String),
+}
+
+impl Breakpoint {
+    fn matches(&self, index: usize, op: &Op) -> bool {
+        match self {
+            Breakpoint::Index(i) => *i == index,
+            Breakpoint::Opcode(mnemonic) => op.mnemonic == mnemonic,
+        }
+    }
+}
+
+// `5` breaks before instruction 5, `jmp` before any jmp.
+impl FromStr for Breakpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<usize>() {
+            Ok(index) => Ok(Breakpoint::Index(index)),
+            Err(_) if !s.is_empty() && s.chars().all(|c| c.is_ascii_alphabetic()) => {
+                Ok(Breakpoint::Opcode(s.to_string()))
+            }
+            Err(_) => Err(format!("bad breakpoint {:?}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cmp {
+    Changed,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+// A watch on the accumulator, like `acc`, `acc > 5` or `acc == 0`. A bare
+// `acc` fires whenever the value changes; comparisons fire on the step where
+// they start to hold, not on every step after.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watch {
+    pub cmp: Cmp,
+    pub value: i64,
+}
+
+impl Watch {
+    fn test(&self, acc: i64) -> bool {
+        match self.cmp {
+            Cmp::Changed => false,
+            Cmp::Eq => acc == self.value,
+            Cmp::Ne => acc != self.value,
+            Cmp::Lt => acc < self.value,
+            Cmp::Le => acc <= self.value,
+            Cmp::Gt => acc > self.value,
+            Cmp::Ge => acc >= self.value,
+        }
+    }
+
+    fn fires(&self, before: i64, after: i64) -> bool {
+        match self.cmp {
+            Cmp::Changed => before != after,
+            _ => !self.test(before) && self.test(after),
+        }
+    }
+}
+
+impl FromStr for Watch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split_whitespace().collect();
+        let (cmp, value) = match parts.as_slice() {
+            ["acc"] => {
+                return Ok(Watch {
+                    cmp: Cmp::Changed,
+                    value: 0,
+                })
+            }
+            ["acc", cmp, value] => (*cmp, *value),
+            _ => return Err(format!("bad watch {:?}", s)),
+        };
+        let cmp = match cmp {
+            "==" => Cmp::Eq,
+            "!=" => Cmp::Ne,
+            "<" => Cmp::Lt,
+            "<=" => Cmp::Le,
+            ">" => Cmp::Gt,
+            ">=" => Cmp::Ge,
+            _ => return Err(format!("bad comparison {:?}", cmp)),
+        };
+        let value = value
+            .parse()
+            .map_err(|_| format!("bad watch value {:?}", value))?;
+        Ok(Watch { cmp, value })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Event {
+    Stepped,
+    Breakpoint(usize),
+    Watch {
+        watch: Watch,
+        before: i64,
+        after: i64,
+    },
+    // Halted or jumped out of the program; loops are reported below.
+    Exited(Termination),
+    // `closed_by` jumped back to `target`, which had already run in the
+    // same state.
+    InfiniteLoop {
+        closed_by: usize,
+        target: usize,
+    },
+}
+
+pub struct Debugger {
+    pub computer: Computer,
+    pub breakpoints: Vec<Breakpoint>,
+    pub watches: Vec<Watch>,
+    // The last `Computer::TRACE_LEN` instructions run, oldest first.
+    pub trace: VecDeque<usize>,
+    steps: usize,
+    detector: LoopDetector,
+}
+
+impl Debugger {
+    pub fn new(computer: Computer) -> Self {
+        let detector = LoopDetector::new(&computer.ops);
+        let mut debugger = Self {
+            computer,
+            breakpoints: vec![],
+            watches: vec![],
+            trace: VecDeque::with_capacity(Computer::TRACE_LEN),
+            steps: 0,
+            detector,
+        };
+        debugger.reset();
+        debugger
+    }
+
+    pub fn reset(&mut self) {
+        self.computer.reset();
+        self.trace.clear();
+        self.steps = 0;
+        // Loops are spotted the same way `Computer::run` spots them.
+        self.detector = LoopDetector::new(&self.computer.ops);
+        self.detector.repeated(&self.computer.ctx);
+    }
+
+    // Runs one instruction, reporting the first thing worth stopping for.
+    // Like `Computer::run`, it gives up once `step_limit` instructions have
+    // run since the last reset.
+    pub fn step(&mut self) -> Result<Event, Error> {
+        if let Some(termination) = self.computer.exited() {
+            return Ok(Event::Exited(termination));
+        }
+        if self.steps >= self.computer.step_limit {
+            return Ok(Event::Exited(Termination::StepLimit));
+        }
+        self.steps += 1;
+        let index = self.computer.ctx.register;
+        let before = self.computer.ctx.accumulator();
+        match self.computer.step() {
+            Ok(_) => (),
+            Err(Error::OutOfBounds(addr)) => {
+                return Ok(Event::Exited(Termination::OutOfBounds(addr)))
+            }
+            Err(error) => return Err(error),
+        }
+        if self.trace.len() == Computer::TRACE_LEN {
+            self.trace.pop_front();
+        }
+        self.trace.push_back(index);
+        let after = self.computer.ctx.accumulator();
+
+        if let Some(termination) = self.computer.exited() {
+            return Ok(Event::Exited(termination));
+        }
+        if self.detector.repeated(&self.computer.ctx) {
+            return Ok(Event::InfiniteLoop {
+                closed_by: index,
+                target: self.computer.ctx.register,
+            });
+        }
+        if let Some(&watch) = self.watches.iter().find(|w| w.fires(before, after)) {
+            return Ok(Event::Watch {
+                watch,
+                before,
+                after,
+            });
+        }
+        let next = self.computer.ctx.register;
+        if self
+            .breakpoints
+            .iter()
+            .any(|b| b.matches(next, &self.computer.ops[next]))
+        {
+            return Ok(Event::Breakpoint(next));
+        }
+        Ok(Event::Stepped)
+    }
+
+    pub fn cont(&mut self) -> Result<Event, Error> {
+        loop {
+            match self.step()? {
+                Event::Stepped => continue,
+                event => return Ok(event),
+            }
+        }
+    }
+
+    // The instructions within `radius` of the pointer, marking the pointer
+    // with `=>` and breakpoints with `*`.
+    pub fn window(&self, radius: usize) -> String {
+        let pointer = self.computer.ctx.register;
+        let ops = &self.computer.ops;
+        let start = pointer.saturating_sub(radius).min(ops.len());
+        let end = (pointer + radius + 1).min(ops.len());
+        (start..end)
+            .map(|i| self.line(i, i == pointer))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn line(&self, index: usize, current: bool) -> String {
+        let op = &self.computer.ops[index];
+        let breakpoint = self.breakpoints.iter().any(|b| b.matches(index, op));
+        format!(
+            "{}{}{:>5}: {}",
+            if current { "=>" } else { "  " },
+            if breakpoint { "*" } else { " " },
+            index,
+            op
+        )
+    }
+
+    // The last `len` executed instructions, oldest first.
+    pub fn recent_trace(&self, len: usize) -> String {
+        let start = self.trace.len().saturating_sub(len);
+        self.trace
+            .iter()
+            .skip(start)
+            .map(|&i| self.line(i, false))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn describe(&self, event: &Event) -> String {
+        match event {
+            Event::Stepped => self.window(0),
+            Event::Breakpoint(index) => format!("breakpoint at {}\n{}", index, self.window(2)),
+            Event::Watch {
+                watch,
+                before,
+                after,
+            } => format!(
+                "watch {:?} {}: acc {} -> {}\n{}",
+                watch.cmp,
+                watch.value,
+                before,
+                after,
+                self.window(2)
+            ),
+            Event::Exited(Termination::OutOfBounds(addr)) => format!(
+                "jumped out of bounds to {} with acc {}",
+                addr,
+                self.computer.ctx.accumulator()
+            ),
+            Event::Exited(Termination::StepLimit) => format!(
+                "gave up after {} steps at {} with acc {}",
+                self.steps,
+                self.computer.ctx.register,
+                self.computer.ctx.accumulator()
+            ),
+            Event::Exited(_) => format!(
+                "halted at {} with acc {}",
+                self.computer.ctx.register,
+                self.computer.ctx.accumulator()
+            ),
+            Event::InfiniteLoop { closed_by, target } => format!(
+                "infinite loop: {} jumped back to {}\ntrace:\n{}",
+                self.line(*closed_by, false).trim_start(),
+                target,
+                self.recent_trace(20)
+            ),
+        }
+    }
+}
+
+const HELP: &str = "\
+break N|OPCODE   b   stop before instruction N or any OPCODE
+delete N|OPCODE  d   remove a breakpoint
+watch acc [CMP N]    stop when acc changes, or when `acc CMP N` starts to hold
+step [N]         s   run N instructions (default 1)
+continue         c   run until a breakpoint, watch, loop or halt
+list [N]         l   show N instructions either side of the pointer
+trace [N]        t   show the last N executed instructions
+print            p   show the pointer and registers
+reset            r   start again from instruction 0
+quit             q";
+
+// Reads commands until `quit` or end of input.
+pub fn repl(
+    debugger: &mut Debugger,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> std::io::Result<()> {
+    writeln!(output, "{}", debugger.window(2))?;
+    for line in input.lines() {
+        let line = line?;
+        let (command, rest) = match line.trim().split_once(' ') {
+            Some((command, rest)) => (command, rest.trim()),
+            None => (line.trim(), ""),
+        };
+        let count = |default: usize| rest.parse().unwrap_or(default);
+
+        let reply = match command {
+            "" => continue,
+            "q" | "quit" => break,
+            "h" | "help" => HELP.to_string(),
+            "b" | "break" => match rest.parse() {
+                Ok(breakpoint) => {
+                    debugger.breakpoints.push(breakpoint);
+                    format!("{} breakpoint(s)", debugger.breakpoints.len())
+                }
+                Err(e) => e,
+            },
+            "d" | "delete" => match rest.parse::<Breakpoint>() {
+                Ok(breakpoint) => {
+                    debugger.breakpoints.retain(|b| *b != breakpoint);
+                    format!("{} breakpoint(s)", debugger.breakpoints.len())
+                }
+                Err(e) => e,
+            },
+            "w" | "watch" => match rest.parse() {
+                Ok(watch) => {
+                    debugger.watches.push(watch);
+                    format!("{} watch(es)", debugger.watches.len())
+                }
+                Err(e) => e,
+            },
+            "s" | "step" => {
+                let mut reply = String::new();
+                for _ in 0..count(1) {
+                    match debugger.step() {
+                        Ok(Event::Stepped) => reply = debugger.describe(&Event::Stepped),
+                        Ok(event) => {
+                            reply = debugger.describe(&event);
+                            break;
+                        }
+                        Err(e) => {
+                            reply = e.to_string();
+                            break;
+                        }
+                    }
+                }
+                reply
+            }
+            "c" | "continue" => match debugger.cont() {
+                Ok(event) => debugger.describe(&event),
+                Err(e) => e.to_string(),
+            },
+            "l" | "list" => debugger.window(count(5)),
+            "t" | "trace" => debugger.recent_trace(count(20)),
+            "p" | "print" => format!(
+                "pointer {} registers {:?}",
+                debugger.computer.ctx.register, debugger.computer.ctx.registers
+            ),
+            "r" | "reset" => {
+                debugger.reset();
+                debugger.window(2)
+            }
+            _ => format!("unknown command {:?}, try help", command),
+        };
+        writeln!(output, "{}", reply)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PROGRAM: &str = "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6";
+
+    fn debugger() -> Debugger {
+        Debugger::new(PROGRAM.parse().unwrap())
+    }
+
+    #[test]
+    fn it_stops_at_breakpoints_and_watches() {
+        let mut debugger = debugger();
+        debugger
+            .breakpoints
+            .push(Breakpoint::Opcode("jmp".to_string()));
+        assert_eq!(debugger.cont().unwrap(), Event::Breakpoint(2));
+        assert_eq!(debugger.cont().unwrap(), Event::Breakpoint(7));
+
+        debugger.reset();
+        debugger.breakpoints.clear();
+        debugger.watches.push("acc >= 2".parse().unwrap());
+        assert_eq!(
+            debugger.cont().unwrap(),
+            Event::Watch {
+                watch: Watch {
+                    cmp: Cmp::Ge,
+                    value: 2
+                },
+                before: 1,
+                after: 2
+            }
+        );
+        assert_eq!(debugger.computer.ctx.register, 7);
+
+        // Watches see the whole register, not just its low 32 bits.
+        let ops = crate::dialect::Dialect::extended()
+            .parse_program("set acc 5000000000\nset acc 705032704")
+            .unwrap();
+        let mut debugger = Debugger::new(Computer::new(ops));
+        debugger.watches.push("acc > 1000000000".parse().unwrap());
+        assert_eq!(
+            debugger.cont().unwrap(),
+            Event::Watch {
+                watch: Watch {
+                    cmp: Cmp::Gt,
+                    value: 1_000_000_000
+                },
+                before: 0,
+                after: 5_000_000_000
+            }
+        );
+        assert_eq!(debugger.cont().unwrap(), Event::Exited(Termination::Halted));
+    }
+
+    #[test]
+    fn it_reports_the_instruction_that_closed_the_loop() {
+        let mut debugger = debugger();
+        assert_eq!(
+            debugger.cont().unwrap(),
+            Event::InfiniteLoop {
+                closed_by: 4,
+                target: 1
+            }
+        );
+        assert_eq!(debugger.trace, vec![0, 1, 2, 6, 7, 3, 4]);
+        assert_eq!(debugger.computer.ctx.accumulator(), 5);
+    }
+
+    #[test]
+    fn it_agrees_with_the_computer_about_loops() {
+        // Each pass reads a new input, so it never repeats a state; it runs
+        // out of input instead.
+        let program = "in r1\nset r1 0\njz r1 -2";
+        let computer = || {
+            let ops = crate::dialect::Dialect::extended()
+                .parse_program(program)
+                .unwrap();
+            let mut computer = Computer::new(ops);
+            computer.ctx.input.extend(vec![5, 6, 7]);
+            computer
+        };
+        assert!(matches!(computer().run(), Err(Error::NoInput(0))));
+        assert!(matches!(
+            Debugger::new(computer()).cont(),
+            Err(Error::NoInput(0))
+        ));
+    }
+
+    #[test]
+    fn it_stops_at_the_step_limit() {
+        // The registers never repeat, so only the step limit ends this.
+        let ops = crate::dialect::Dialect::extended()
+            .parse_program("add r1 1\njnz r1 -1")
+            .unwrap();
+        let mut computer = Computer::new(ops);
+        computer.step_limit = 3000;
+        let mut debugger = Debugger::new(computer);
+        assert_eq!(
+            debugger.cont().unwrap(),
+            Event::Exited(Termination::StepLimit)
+        );
+        assert_eq!(debugger.computer.ctx.registers[1], 1500);
+        assert_eq!(debugger.trace.len(), Computer::TRACE_LEN);
+        assert_eq!(debugger.trace.back(), Some(&1));
+
+        debugger.reset();
+        assert_eq!(debugger.step().unwrap(), Event::Stepped);
+        assert_eq!(debugger.trace, vec![0]);
+    }
+
+    #[test]
+    fn it_runs_commands() {
+        let mut debugger = debugger();
+        let mut output = vec![];
+        repl(
+            &mut debugger,
+            "b 6\nc\ns 2\nl 1\nq\ns".as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("breakpoint at 6"));
+        assert!(output.contains("=>     3: acc +3"));
+        assert_eq!(debugger.computer.ctx.register, 3);
+    }
+}
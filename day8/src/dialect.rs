@@ -0,0 +1,340 @@
+use crate::computer::{Ctx, Error, Op, Operand, Register};
+use std::collections::HashMap;
+
+// What an instruction does with the pointer once it has run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Next,
+    Jump(i64),
+    Halt,
+}
+
+// Runs one instruction against the machine, given its parsed operands.
+pub type Handler = fn(&mut Ctx, &[Operand]) -> Result<Flow, Error>;
+
+// How an operand is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arg {
+    // A signed number like `+4`.
+    Offset,
+    Register,
+    // A register or a number.
+    Value,
+}
+
+// Where an opcode can send the pointer, for analyses like `Cfg` that look at
+// a program without running it. Jumps and branches take their offset from
+// their last operand; a branch may also fall through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Next,
+    Jump,
+    Branch,
+    Halt,
+}
+
+// Everything a dialect knows about an opcode: its operands, where it can go
+// and what it does.
+#[derive(Debug, Clone, Copy)]
+pub struct Opcode {
+    pub args: &'static [Arg],
+    pub control: Control,
+    pub execute: Handler,
+}
+
+fn offset(operand: Operand) -> Result<i64, Error> {
+    match operand {
+        Operand::Imm(offset) => Ok(offset),
+        Operand::Reg(register) => Err(Error::CantParse(format!(
+            "expected an offset, not {}",
+            register
+        ))),
+    }
+}
+
+fn jmp(_: &mut Ctx, args: &[Operand]) -> Result<Flow, Error> {
+    Ok(Flow::Jump(offset(args[0])?))
+}
+
+fn nop(_: &mut Ctx, _: &[Operand]) -> Result<Flow, Error> {
+    Ok(Flow::Next)
+}
+
+pub const JMP: Opcode = Opcode {
+    args: &[Arg::Offset],
+    control: Control::Jump,
+    execute: jmp,
+};
+
+pub const NOP: Opcode = Opcode {
+    args: &[Arg::Offset],
+    control: Control::Next,
+    execute: nop,
+};
+
+fn branch_if(condition: bool, operand: Operand) -> Result<Flow, Error> {
+    if condition {
+        Ok(Flow::Jump(offset(operand)?))
+    } else {
+        Ok(Flow::Next)
+    }
+}
+
+// The opcodes a program may use. The default dialect is the handheld's own
+// `acc`/`jmp`/`nop`; `extended` adds registers, arithmetic, conditional
+// jumps, `hlt` and the `in`/`out` ports. Each entry carries its own
+// behaviour, so `register` can add whole new instructions as well as aliases.
+pub struct Dialect {
+    opcodes: HashMap<&'static str, Opcode>,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        let mut dialect = Self {
+            opcodes: HashMap::new(),
+        };
+        dialect.register(
+            "acc",
+            Opcode {
+                args: &[Arg::Offset],
+                control: Control::Next,
+                execute: |ctx, args| {
+                    let value = ctx.value(args[0]);
+                    let acc = &mut ctx.registers[Register::ACC.0];
+                    *acc = acc.wrapping_add(value);
+                    Ok(Flow::Next)
+                },
+            },
+        );
+        dialect.register("jmp", JMP);
+        dialect.register("nop", NOP);
+        dialect
+    }
+}
+
+impl Dialect {
+    pub fn extended() -> Self {
+        use Arg::*;
+
+        let mut dialect = Self::default();
+        let mut arithmetic = |mnemonic, execute| {
+            dialect.register(
+                mnemonic,
+                Opcode {
+                    args: &[Register, Value],
+                    control: Control::Next,
+                    execute,
+                },
+            )
+        };
+        arithmetic("set", |ctx, args| {
+            *ctx.register_mut(args[0])? = ctx.value(args[1]);
+            Ok(Flow::Next)
+        });
+        arithmetic("add", |ctx, args| {
+            let value = ctx.value(args[1]);
+            let register = ctx.register_mut(args[0])?;
+            *register = register.wrapping_add(value);
+            Ok(Flow::Next)
+        });
+        arithmetic("sub", |ctx, args| {
+            let value = ctx.value(args[1]);
+            let register = ctx.register_mut(args[0])?;
+            *register = register.wrapping_sub(value);
+            Ok(Flow::Next)
+        });
+        arithmetic("mul", |ctx, args| {
+            let value = ctx.value(args[1]);
+            let register = ctx.register_mut(args[0])?;
+            *register = register.wrapping_mul(value);
+            Ok(Flow::Next)
+        });
+
+        dialect.register(
+            "jz",
+            Opcode {
+                args: &[Value, Offset],
+                control: Control::Branch,
+                execute: |ctx, args| branch_if(ctx.value(args[0]) == 0, args[1]),
+            },
+        );
+        dialect.register(
+            "jnz",
+            Opcode {
+                args: &[Value, Offset],
+                control: Control::Branch,
+                execute: |ctx, args| branch_if(ctx.value(args[0]) != 0, args[1]),
+            },
+        );
+        dialect.register(
+            "hlt",
+            Opcode {
+                args: &[],
+                control: Control::Halt,
+                execute: |_, _| Ok(Flow::Halt),
+            },
+        );
+        dialect.register(
+            "in",
+            Opcode {
+                args: &[Register],
+                control: Control::Next,
+                execute: |ctx, args| {
+                    let value = ctx.input.pop_front().ok_or(Error::NoInput(ctx.register))?;
+                    *ctx.register_mut(args[0])? = value;
+                    Ok(Flow::Next)
+                },
+            },
+        );
+        dialect.register(
+            "out",
+            Opcode {
+                args: &[Value],
+                control: Control::Next,
+                execute: |ctx, args| {
+                    let value = ctx.value(args[0]);
+                    ctx.output.push(value);
+                    Ok(Flow::Next)
+                },
+            },
+        );
+        dialect
+    }
+
+    pub fn register(&mut self, mnemonic: &'static str, opcode: Opcode) {
+        self.opcodes.insert(mnemonic, opcode);
+    }
+
+    fn operand(arg: Arg, s: &str) -> Result<Operand, Error> {
+        match arg {
+            Arg::Offset => s
+                .parse::<i32>()
+                .map(|offset| Operand::Imm(offset as i64))
+                .map_err(|e| Error::CantParse(format!("can't parse number {}", e))),
+            Arg::Register => s.parse().map(Operand::Reg),
+            Arg::Value => s.parse(),
+        }
+    }
+
+    pub fn parse(&self, line: &str) -> Result<Op, Error> {
+        let mut parts = line.split_whitespace();
+        let mnemonic = parts
+            .next()
+            .ok_or_else(|| Error::CantParse(format!("can't find op {}", line)))?;
+        let (&mnemonic, &opcode) = self
+            .opcodes
+            .get_key_value(mnemonic)
+            .ok_or_else(|| Error::UnknownOpcode(line.to_string()))?;
+
+        let args: Vec<&str> = parts.collect();
+        if args.len() != opcode.args.len() {
+            return Err(Error::CantParse(format!(
+                "expected {} operands but found {}",
+                opcode.args.len(),
+                args.len()
+            )));
+        }
+        let operands = opcode
+            .args
+            .iter()
+            .zip(args)
+            .map(|(&arg, s)| Self::operand(arg, s))
+            .collect::<Result<_, _>>()?;
+        Ok(Op {
+            mnemonic,
+            operands,
+            opcode,
+        })
+    }
+
+    // Blank lines and `#` comments are skipped.
+    pub fn parse_program(&self, s: &str) -> Result<Vec<Op>, Error> {
+        s.lines()
+            .map(|line| line.split('#').next().unwrap_or_default().trim())
+            .filter(|line| !line.is_empty())
+            .map(|line| self.parse(line))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::computer::{Computer, Termination};
+
+    #[test]
+    fn it_parses_the_default_dialect() {
+        let dialect = Dialect::default();
+        let op = dialect.parse("acc +15").unwrap();
+        assert_eq!((op.mnemonic, op.operands), ("acc", vec![Operand::Imm(15)]));
+        assert_eq!(dialect.parse("jmp -3").unwrap().to_string(), "jmp -3");
+        assert!(matches!(dialect.parse("hlt"), Err(Error::UnknownOpcode(_))));
+        assert!(matches!(
+            dialect.parse("acc +1 +2"),
+            Err(Error::CantParse(_))
+        ));
+        assert!(matches!(dialect.parse("acc r1"), Err(Error::CantParse(_))));
+    }
+
+    #[test]
+    fn it_parses_extended_opcodes() {
+        let dialect = Dialect::extended();
+        assert_eq!(
+            dialect.parse("add r3 acc").unwrap().operands,
+            vec![Operand::Reg(Register(3)), Operand::Reg(Register::ACC)]
+        );
+        let op = dialect.parse("jnz r1 -2").unwrap();
+        assert_eq!(
+            op.operands,
+            vec![Operand::Reg(Register(1)), Operand::Imm(-2)]
+        );
+        assert!(op.is_conditional());
+        assert!(matches!(
+            dialect.parse("set r8 1"),
+            Err(Error::CantParse(_))
+        ));
+        assert!(matches!(dialect.parse("set 1 1"), Err(Error::CantParse(_))));
+    }
+
+    #[test]
+    fn it_registers_new_opcodes() {
+        // Doubles the accumulator, then jumps by the offset if it's positive.
+        let mut dialect = Dialect::default();
+        dialect.register(
+            "dbl",
+            Opcode {
+                args: &[],
+                control: Control::Next,
+                execute: |ctx, _| {
+                    ctx.registers[Register::ACC.0] *= 2;
+                    Ok(Flow::Next)
+                },
+            },
+        );
+        dialect.register(
+            "jgz",
+            Opcode {
+                args: &[Arg::Offset],
+                control: Control::Branch,
+                execute: |ctx, args| branch_if(ctx.accumulator() > 0, args[0]),
+            },
+        );
+
+        let ops = dialect
+            .parse_program("acc +3\n# comment\n\ndbl # again\nacc -7\njgz -2\nacc +100")
+            .unwrap();
+        assert_eq!(ops[1].to_string(), "dbl");
+        let mut computer = Computer::new(ops);
+        assert_eq!(computer.run().unwrap(), Termination::Halted);
+        // 3 -> 6 -> -1, so the jump back never happens.
+        assert_eq!(computer.ctx.accumulator(), 99);
+
+        let ops = dialect
+            .parse_program("acc +4\ndbl\nacc -7\njgz -2")
+            .unwrap();
+        let mut computer = Computer::new(ops);
+        assert_eq!(computer.run().unwrap(), Termination::Halted);
+        // 4 -> 8 -> 1 -> 2 -> -5.
+        assert_eq!(computer.ctx.accumulator(), -5);
+    }
+}
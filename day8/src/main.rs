@@ -1,193 +1,107 @@
-use std::str::FromStr;
+mod cfg;
+mod computer;
+mod debugger;
+mod dialect;
 
-enum Op {
-    Acc(i32),
-    Jmp(i32),
-    Nop(i32),
-}
-
-#[derive(Debug)]
-enum Error {
-    CantParse(String),
-    CouldntGetOps(usize, String),
-    InfiniteLoop,
-}
-
-impl std::fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", &self)
-    }
-}
+use computer::{Computer, Termination};
+use debugger::Debugger;
+use dialect::Dialect;
+use std::convert::TryFrom;
 
-impl std::error::Error for Error {}
-
-impl FromStr for Op {
-    type Err = Error;
-
-    fn from_str(line: &str) -> Result<Self, Self::Err> {
-        let mut parts = line.splitn(2, " ");
-        let op = parts
-            .next()
-            .ok_or(Error::CantParse(format!("can't find op {}", line)))?;
-        let number = parts
-            .next()
-            .ok_or(Error::CantParse(format!("can't find number {}", line)))?
-            .parse::<i32>()
-            .map_err(|e| Error::CantParse(format!("can't parse number {}", e)))?;
-
-        use Op::*;
-        Ok(match op {
-            "acc" => Acc(number),
-            "jmp" => Jmp(number),
-            "nop" => Nop(number),
-            _ => return Err(Error::CantParse(format!("unknown operation: {}", line))),
-        })
+// Only the first flip is run; `cfg::flips` lists every candidate.
+fn part2(computer: &mut Computer) -> Option<i32> {
+    let flip = cfg::flips(&computer.ops).into_iter().next()?;
+    computer.ops[flip.index] = flip.to;
+    let termination = computer.run().ok();
+    computer.ops[flip.index] = flip.from;
+    match termination {
+        Some(Termination::Halted) => i32::try_from(computer.ctx.accumulator()).ok(),
+        _ => None,
     }
 }
 
-impl Op {
-    fn run(&self, ctx: &mut Ctx) {
-        use Op::*;
-        match self {
-            Jmp(val) => ctx.register = (ctx.register as i32 + val) as usize,
-            Acc(val) => {
-                ctx.accumulator += val;
-                ctx.register += 1;
+// --run FILE runs a program in the extended dialect, feeding it the
+// comma-separated numbers given with --input and giving up after --steps
+// instructions (a million by default). --debug opens the debugger on
+// that program instead, or on the puzzle input if there is none, and --flips
+// lists every jmp/nop swap that would make it terminate.
+fn run_program(
+    mut args: impl Iterator<Item = String>,
+    puzzle: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut program = None;
+    let mut input = vec![];
+    let mut debug = false;
+    let mut list_flips = false;
+    let mut step_limit = Computer::DEFAULT_STEP_LIMIT;
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--debug" => {
+                debug = true;
+                continue;
             }
-            Nop(_) => {
-                ctx.register += 1;
+            "--flips" => {
+                list_flips = true;
+                continue;
             }
+            _ => (),
         }
-    }
-}
-
-struct Computer {
-    ops: Vec<Op>,
-    ctx: Ctx,
-}
-
-struct Ctx {
-    accumulator: i32,
-    register: usize,
-}
-
-impl FromStr for Computer {
-    type Err = Error;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let ops: Vec<Op> = s
-            .lines()
-            .map(|line| line.parse())
-            .collect::<Result<_, _>>()?;
-
-        Ok(Self {
-            ops,
-            ctx: Ctx {
-                accumulator: 0,
-                register: 0,
-            },
-        })
-    }
-}
-
-impl Computer {
-    fn step(&mut self) -> Result<(usize, i32), Error> {
-        let op = &self.ops.get(self.ctx.register).ok_or(Error::CouldntGetOps(
-            self.ctx.register,
-            format!(
-                "tried to get {} but len is only {}",
-                self.ctx.register,
-                self.ops.len()
-            ),
-        ))?;
-        op.run(&mut self.ctx);
-        Ok((self.ctx.register, self.ctx.accumulator))
-    }
-
-    fn run(&mut self) -> Result<i32, Error> {
-        self.reset();
-        let mut map: std::collections::HashSet<usize> = std::collections::HashSet::new();
-
-        loop {
-            match self.step() {
-                Ok((register, _)) => {
-                    if !map.insert(register) {
-                        return Err(Error::InfiniteLoop);
-                    };
-                }
-                Err(Error::CouldntGetOps(_, _)) => return Ok(self.ctx.accumulator),
-                Err(error) => return Err(error),
+        let value = args
+            .next()
+            .ok_or_else(|| format!("missing value for {}", flag))?;
+        match flag.as_str() {
+            "--steps" => step_limit = value.parse()?,
+            "--run" => program = Some(std::fs::read_to_string(value)?),
+            "--input" => {
+                input = value
+                    .split(',')
+                    .map(|n| n.trim().parse::<i64>())
+                    .collect::<Result<_, _>>()?
             }
+            _ => return Err(format!("unknown flag {}", flag).into()),
         }
     }
 
-    fn reset(&mut self) {
-        self.ctx.accumulator = 0;
-        self.ctx.register = 0;
-    }
-}
-
-fn part2(computer: &mut Computer) -> Option<i32> {
-    for i in 0..computer.ops.len() {
-        let mut tmp: Op;
-        match computer.ops.get_mut(i) {
-            Some(jump @ Op::Jmp(_)) => {
-                let nop_val = if let Op::Jmp(val) = jump {
-                    *val
-                } else {
-                    unreachable!()
-                };
-
-                tmp = Op::Nop(nop_val);
-                std::mem::swap(jump, &mut tmp);
-            }
-            Some(nop @ Op::Nop(_)) => {
-                let jump_val = if let Op::Nop(val) = nop {
-                    *val
-                } else {
-                    unreachable!()
-                };
-
-                tmp = Op::Jmp(jump_val);
-                std::mem::swap(nop, &mut tmp);
-            }
-            _ => continue,
-        }
-
-        match computer.run() {
-            Ok(accumulator) => {
-                return Some(accumulator)
-            },
-            _ => {
-                let op = computer
-                    .ops
-                    .get_mut(i)
-                    .expect(&format!("couldn't get access to ops {}", i)[..]);
-                std::mem::swap(op, &mut tmp);
-            }
+    let mut computer = match &program {
+        Some(program) => Computer::new(Dialect::extended().parse_program(program)?),
+        None if debug || list_flips => puzzle.parse()?,
+        None => return Ok(()),
+    };
+    computer.ctx.input.extend(input);
+    computer.step_limit = step_limit;
+
+    if list_flips {
+        for flip in cfg::flips(&computer.ops) {
+            println!("{}: {} -> {}", flip.index, flip.from, flip.to);
         }
+    } else if debug {
+        let stdin = std::io::stdin();
+        debugger::repl(
+            &mut Debugger::new(computer),
+            stdin.lock(),
+            std::io::stdout(),
+        )?;
+    } else {
+        let termination = computer.run()?;
+        println!("{:?} with acc {}", termination, computer.ctx.accumulator());
+        println!("output: {:?}", computer.ctx.output);
+        println!("registers: {:?}", computer.ctx.registers);
     }
-
-    None
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let input = std::fs::read_to_string("input.txt")?;
     let mut computer: Computer = input.parse()?;
-    let mut map: std::collections::HashSet<usize> = std::collections::HashSet::new();
-    let mut last_accumulator = 0;
-
-    loop {
-        let (register, accumulator): (usize, i32) = computer.step()?;
-        if !map.insert(register) {
-            break;
-        };
-        last_accumulator = accumulator
-    }
-    println!("part1: {}", last_accumulator);
+    let part1 = match computer.run()? {
+        Termination::InfiniteLoop { .. } => i32::try_from(computer.ctx.accumulator())?,
+        termination => return Err(format!("expected a loop, but got {:?}", termination).into()),
+    };
+    println!("part1: {}", part1);
     println!(
         "part2: {}",
         part2(&mut computer).expect("couldn't find result for part 2")
     );
-    Ok(())
+
+    run_program(std::env::args().skip(1), &input)
 }
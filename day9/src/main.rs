@@ -1,94 +1,70 @@
-#[derive(Debug)]
-enum Error {
+mod ranges;
+mod xmas;
+
+use ranges::contiguous_ranges;
+use xmas::XmasValidator;
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
     NoResult,
+    BadNumber { line: usize, found: String },
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self)
-    }
-}
-
-impl std::error::Error for Error {}
-type Map = std::collections::HashMap<i64, Vec<usize>>;
-fn part1(nums: &Vec<i64>, preamble_length: usize) -> Result<i64, Box<dyn std::error::Error>> {
-    let mut previous25: std::collections::HashMap<i64, Vec<usize>> = nums
-        .iter()
-        .enumerate()
-        .take(preamble_length)
-        .fold(std::collections::HashMap::new(), |mut memo, (i, n)| {
-            memo.entry(*n).or_insert(vec![]).push(i);
-            memo
-        });
-
-    fn check_digit(map: &Map, num: &i64) -> bool {
-        for key in map.keys() {
-            let complement = (key - num).abs();
-            if let Some(pos) = map.get(&complement) {
-                if &complement == key {
-                    if pos.len() > 1 {
-                        return true;
-                    } else {
-                        continue;
-                    };
-                } else {
-                    return true;
-                }
-            } else {
-                continue;
+        match self {
+            Error::NoResult => write!(f, "no result"),
+            Error::BadNumber { line, found } => {
+                write!(f, "line {}: {:?} is not a number", line, found)
             }
         }
-        false
     }
+}
 
-    for i in preamble_length..nums.len() {
-        let dropped = nums[i - preamble_length];
-        let check = nums[i];
+impl std::error::Error for Error {}
 
-        if check_digit(&previous25, &check) {
-            if previous25
-                .get(&dropped)
-                .expect("can't get num about to be dropped")
-                .len()
-                == 1
-            {
-                previous25.remove(&dropped);
-            } else {
-                previous25.get_mut(&dropped).unwrap().remove(0);
-            }
-            previous25.entry(check).or_insert(vec![]).push(i);
-            continue;
-        } else {
-            return Ok(check);
-        }
-    }
-    Err(Box::new(Error::NoResult))
+// One number per line; blank lines are skipped.
+pub fn parse_numbers(input: &str) -> Result<Vec<i64>, Error> {
+    input
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(i, line)| {
+            line.parse::<i64>().map_err(|_| Error::BadNumber {
+                line: i + 1,
+                found: line.to_string(),
+            })
+        })
+        .collect()
 }
 
-fn part2(nums: &Vec<i64>, num: &i64) -> Result<i64, Box<dyn std::error::Error>> {
-    for start in 0..nums.len() {
-        let mut total = 0_i64;
+fn part1(nums: &[i64], preamble_length: usize) -> Result<i64, Box<dyn std::error::Error>> {
+    XmasValidator::new(nums.iter().copied(), preamble_length)
+        .next()
+        .map(|invalid| invalid.value)
+        .ok_or_else(|| Box::new(Error::NoResult).into())
+}
 
-        for next in start..nums.len() {
-            total += nums[next];
-            if &total == num {
-                return Ok(nums[start..=next].iter().max().unwrap()
-                    + nums[start..=next].iter().min().unwrap());
-            } else if &total > num {
-                break;
-            }
-        }
-    }
+// The first run of two or more numbers summing to `num`, as an inclusive
+// `(start, end)` range.
+pub fn contiguous_range(nums: &[i64], num: i64) -> Option<(usize, usize)> {
+    contiguous_ranges(nums, num)
+        .first()
+        .map(|range| (range.start, range.end))
+}
 
-    Err(Box::new(Error::NoResult))
+fn part2(nums: &[i64], num: &i64) -> Result<i64, Box<dyn std::error::Error>> {
+    let range = contiguous_ranges(nums, *num)
+        .into_iter()
+        .next()
+        .ok_or(Error::NoResult)?;
+    Ok(range.min + range.max)
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let input = std::fs::read_to_string("input.txt")?;
-    let nums = input
-        .lines()
-        .map(|num| num.parse::<i64>())
-        .collect::<Result<Vec<i64>, _>>()?;
+    let nums = parse_numbers(&input)?;
 
     let part1 = part1(&nums, 25)?;
     println!("part1: {}", part1);
@@ -99,9 +75,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 #[cfg(test)]
 mod test {
     use super::*;
-    #[test]
-    fn it_works() {
-        let input = r#"35
+
+    const INPUT: &str = r#"35
 20
 15
 25
@@ -121,31 +96,29 @@ mod test {
 277
 309
 576"#;
-        assert_eq!(part1(&input, 5).unwrap(), 127_i64);
+
+    #[test]
+    fn it_works() {
+        let nums = parse_numbers(INPUT).unwrap();
+        assert_eq!(part1(&nums, 5).unwrap(), 127_i64);
     }
 
     #[test]
     fn it_works_part2() {
-        let input = r#"35
-20
-15
-25
-47
-40
-62
-55
-65
-95
-102
-117
-150
-182
-127
-219
-299
-277
-309
-576"#;
-        assert_eq!(part2(&input, &127).unwrap(), 62_i64);
+        let nums = parse_numbers(INPUT).unwrap();
+        assert_eq!(contiguous_range(&nums, 127), Some((2, 5)));
+        assert_eq!(part2(&nums, &127).unwrap(), 62_i64);
+    }
+
+    #[test]
+    fn it_rejects_bad_numbers() {
+        assert_eq!(parse_numbers("1\n\n-2\n"), Ok(vec![1, -2]));
+        assert_eq!(
+            parse_numbers("1\ntwo"),
+            Err(Error::BadNumber {
+                line: 2,
+                found: "two".to_string()
+            })
+        );
     }
 }
@@ -0,0 +1,166 @@
+use std::collections::{HashMap, VecDeque};
+
+// An inclusive run of two or more numbers summing to the target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SumRange {
+    pub start: usize,
+    pub end: usize,
+    pub min: i64,
+    pub max: i64,
+}
+
+// Sliding window min/max: each deque holds indices whose values are
+// monotonic, so the front is always the answer for the current window.
+struct MinMax<'a> {
+    nums: &'a [i64],
+    mins: VecDeque<usize>,
+    maxes: VecDeque<usize>,
+    start: usize,
+    end: usize,
+}
+
+impl<'a> MinMax<'a> {
+    fn new(nums: &'a [i64]) -> Self {
+        Self {
+            nums,
+            mins: VecDeque::new(),
+            maxes: VecDeque::new(),
+            start: 0,
+            end: 0,
+        }
+    }
+
+    fn push(&mut self) {
+        let value = self.nums[self.end];
+        while self.mins.back().is_some_and(|&i| self.nums[i] >= value) {
+            self.mins.pop_back();
+        }
+        while self.maxes.back().is_some_and(|&i| self.nums[i] <= value) {
+            self.maxes.pop_back();
+        }
+        self.mins.push_back(self.end);
+        self.maxes.push_back(self.end);
+        self.end += 1;
+    }
+
+    fn pop(&mut self) {
+        if self.mins.front() == Some(&self.start) {
+            self.mins.pop_front();
+        }
+        if self.maxes.front() == Some(&self.start) {
+            self.maxes.pop_front();
+        }
+        self.start += 1;
+    }
+
+    // Moves the window to `start..=end`. Both ends only go forward for ranges
+    // sorted by start, unless a range ends before the previous one did; then
+    // the window starts over.
+    fn slide(&mut self, start: usize, end: usize) -> (i64, i64) {
+        if end + 1 < self.end {
+            self.mins.clear();
+            self.maxes.clear();
+            self.start = start;
+            self.end = start;
+        }
+        while self.end <= end {
+            self.push();
+        }
+        while self.start < start {
+            self.pop();
+        }
+        (self.nums[self.mins[0]], self.nums[self.maxes[0]])
+    }
+}
+
+// Every run of at least two numbers that sums to `target`, ordered by start
+// then end. `nums[i..j]` sums to `target` when `prefix[j] - prefix[i]` does,
+// so one pass with a map from prefix sums to positions finds them all, with
+// or without negative numbers.
+pub fn contiguous_ranges(nums: &[i64], target: i64) -> Vec<SumRange> {
+    let mut prefix = Vec::with_capacity(nums.len() + 1);
+    prefix.push(0_i128);
+    for &n in nums {
+        prefix.push(prefix[prefix.len() - 1] + n as i128);
+    }
+
+    let mut seen: HashMap<i128, Vec<usize>> = HashMap::new();
+    let mut found = vec![];
+    for end in 2..prefix.len() {
+        // Only positions at least two back, so every range has two numbers.
+        seen.entry(prefix[end - 2]).or_default().push(end - 2);
+        if let Some(starts) = seen.get(&(prefix[end] - target as i128)) {
+            found.extend(starts.iter().map(|&start| (start, end - 1)));
+        }
+    }
+    found.sort_unstable();
+
+    let mut window = MinMax::new(nums);
+    found
+        .into_iter()
+        .map(|(start, end)| {
+            let (min, max) = window.slide(start, end);
+            SumRange {
+                start,
+                end,
+                min,
+                max,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn brute_force(nums: &[i64], target: i64) -> Vec<SumRange> {
+        let mut found = vec![];
+        for start in 0..nums.len() {
+            for end in start + 1..nums.len() {
+                let range = &nums[start..=end];
+                if range.iter().sum::<i64>() == target {
+                    found.push(SumRange {
+                        start,
+                        end,
+                        min: *range.iter().min().unwrap(),
+                        max: *range.iter().max().unwrap(),
+                    });
+                }
+            }
+        }
+        found
+    }
+
+    #[test]
+    fn it_finds_the_puzzle_range() {
+        let nums = [
+            35, 20, 15, 25, 47, 40, 62, 55, 65, 95, 102, 117, 150, 182, 127, 219, 299, 277, 309,
+            576,
+        ];
+        assert_eq!(
+            contiguous_ranges(&nums, 127),
+            vec![SumRange {
+                start: 2,
+                end: 5,
+                min: 15,
+                max: 47
+            }]
+        );
+    }
+
+    #[test]
+    fn it_finds_every_range_with_negative_numbers() {
+        let nums = [3, -1, 4, 0, -3, 7, 0, 0, 5, -5, 2];
+        for target in -5..=10 {
+            assert_eq!(
+                contiguous_ranges(&nums, target),
+                brute_force(&nums, target),
+                "target {}",
+                target
+            );
+        }
+        // A single number equal to the target doesn't count.
+        assert_eq!(contiguous_ranges(&[5, 1], 5), vec![]);
+    }
+}
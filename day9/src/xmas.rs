@@ -0,0 +1,125 @@
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Invalid {
+    pub position: usize,
+    pub value: i64,
+}
+
+// The last `preamble` numbers as a multiset, so a pair can use the same value
+// twice only if it really appears twice.
+struct Window {
+    numbers: VecDeque<i64>,
+    counts: HashMap<i64, usize>,
+}
+
+impl Window {
+    fn push(&mut self, num: i64) {
+        self.numbers.push_back(num);
+        *self.counts.entry(num).or_insert(0) += 1;
+    }
+
+    fn pop(&mut self) {
+        if let Some(num) = self.numbers.pop_front() {
+            if let Some(count) = self.counts.get_mut(&num) {
+                *count -= 1;
+                if *count == 0 {
+                    self.counts.remove(&num);
+                }
+            }
+        }
+    }
+
+    fn has_pair_summing_to(&self, target: i64) -> bool {
+        self.counts
+            .iter()
+            .any(|(&num, &count)| match target.checked_sub(num) {
+                Some(complement) if complement == num => count > 1,
+                Some(complement) => self.counts.contains_key(&complement),
+                None => false,
+            })
+    }
+}
+
+// Checks each number after the preamble against the `preamble` numbers
+// before it, yielding every one that isn't the sum of two of them.
+pub struct XmasValidator<I> {
+    numbers: I,
+    preamble: usize,
+    window: Window,
+    position: usize,
+}
+
+impl<I: Iterator<Item = i64>> XmasValidator<I> {
+    pub fn new(numbers: I, preamble: usize) -> Self {
+        Self {
+            numbers,
+            preamble,
+            window: Window {
+                numbers: VecDeque::with_capacity(preamble + 1),
+                counts: HashMap::new(),
+            },
+            position: 0,
+        }
+    }
+}
+
+impl<I: Iterator<Item = i64>> Iterator for XmasValidator<I> {
+    type Item = Invalid;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for value in &mut self.numbers {
+            let position = self.position;
+            self.position += 1;
+
+            let valid = position < self.preamble || self.window.has_pair_summing_to(value);
+            self.window.push(value);
+            if self.window.numbers.len() > self.preamble {
+                self.window.pop();
+            }
+            if !valid {
+                return Some(Invalid { position, value });
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn invalid(numbers: &[i64], preamble: usize) -> Vec<(usize, i64)> {
+        XmasValidator::new(numbers.iter().copied(), preamble)
+            .map(|invalid| (invalid.position, invalid.value))
+            .collect()
+    }
+
+    #[test]
+    fn it_reports_every_invalid_number() {
+        let numbers = [
+            35, 20, 15, 25, 47, 40, 62, 55, 65, 95, 102, 117, 150, 182, 127, 219, 299, 277, 309,
+            576,
+        ];
+        assert_eq!(invalid(&numbers, 5), vec![(14, 127)]);
+        assert_eq!(
+            invalid(&[1, 2, 3, 100, 5, 200], 2),
+            vec![(3, 100), (4, 5), (5, 200)]
+        );
+    }
+
+    #[test]
+    fn it_needs_a_sum_not_a_difference() {
+        // 7 - 3 = 4 but nothing sums to 4.
+        assert_eq!(invalid(&[3, 7, 4], 2), vec![(2, 4)]);
+        assert_eq!(invalid(&[-3, 7, 4], 2), vec![]);
+    }
+
+    #[test]
+    fn it_only_reuses_a_number_that_appears_twice() {
+        assert_eq!(invalid(&[5, 1, 10], 2), vec![(2, 10)]);
+        assert_eq!(invalid(&[5, 5, 10], 2), vec![]);
+        // The first 5 has left the window by the time 10 is checked.
+        assert_eq!(invalid(&[5, 5, 1, 10], 2), vec![(2, 1), (3, 10)]);
+    }
+}